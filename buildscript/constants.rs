@@ -157,6 +157,34 @@ pub fn write(target: &Path) {
 
 
 
+	// ASCII names, for terminals that can't show unicode.
+	// This is the first ASCII string of each constant.
+	writeln!(file,
+		concat!(
+			"\tpub fn to_ascii_string(&self) -> String {{\n",
+			"\t\tString::from(match self {{"
+		)
+	).unwrap();
+
+	for c in constants {
+		if !c["strings"].is_array() { continue; }
+		let s = c["strings"].as_array().unwrap();
+		if s[0].as_str().unwrap().is_ascii() { continue; }
+
+		writeln!(file,
+			"\t\t\tConstant::{e} => \"{a}\",",
+			e = c["enum_name"].as_str().unwrap(),
+			a = s.iter().map(|x| x.as_str().unwrap()).find(|x| x.is_ascii()).unwrap()
+		).unwrap();
+	}
+
+	writeln!(file, "\t\t\t_ => return self.to_string()\n\t\t}})\n\t}}\n").unwrap();
+
+
+
+
+
+
 	writeln!(file,
 		concat!(
 			"\tpub fn pretty_name(&self) -> Option<&'static str> {{\n",
@@ -7,6 +7,13 @@ use toml::Value;
 
 
 
+/// Escape a unit string so it may be
+/// written inside a Rust string literal.
+fn escape(s: &str) -> String {
+	s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+
 /// Create WholeUnit enum with
 /// basic impls. Should only be run once.
 fn write_wholeunit_main(mut file: &File, units: &Vec<Value>) {
@@ -41,7 +48,7 @@ fn write_wholeunit_main(mut file: &File, units: &Vec<Value>) {
 	for u in units {
 		writeln!(file,
 			"\t\t\tWholeUnit::{e} => \"{s}\",",
-			s = u["strings"].as_array().unwrap()[0][0].as_str().unwrap(),
+			s = escape(u["strings"].as_array().unwrap()[0][0].as_str().unwrap()),
 			e = u["enum_name"].as_str().unwrap()
 		).unwrap();
 	}
@@ -59,17 +66,178 @@ fn write_wholeunit_main(mut file: &File, units: &Vec<Value>) {
 	).unwrap();
 
 	for u in units {
-		if u.as_table().unwrap().contains_key("no_space") {
-			if u.as_table().unwrap()["no_space"].as_bool().unwrap() {
+		if u.get("no_space").is_some_and(|x| x.as_bool().unwrap()) {
+			writeln!(file,
+				"\t\t\tWholeUnit::{} => true,",
+				u["enum_name"].as_str().unwrap()
+			).unwrap();
+		}
+	}
+
+	writeln!(file, "\t\t\t_ => false\n\t\t}}\n\t}}\n").unwrap();
+
+
+	// ASCII names, for terminals that can't show unicode.
+	// This is the first ASCII string of each unit.
+	writeln!(file,
+		concat!(
+			"\tfn to_ascii_string(&self) -> String {{\n",
+			"\t\tString::from(match self {{"
+		)
+	).unwrap();
+
+	for u in units {
+		let s = u["strings"].as_array().unwrap()[0][0].as_str().unwrap();
+		if s.is_ascii() { continue; }
+
+		let a = u["strings"].as_array().unwrap().iter()
+			.map(|x| x[0].as_str().unwrap())
+			.find(|x| x.is_ascii())
+			.unwrap();
+
+		writeln!(file,
+			"\t\t\tWholeUnit::{e} => \"{a}\",",
+			e = u["enum_name"].as_str().unwrap()
+		).unwrap();
+	}
+
+	writeln!(file, "\t\t\t_ => return self.to_string()\n\t\t}})\n\t}}\n").unwrap();
+
+
+	// Units we may simplify results to
+	writeln!(file,
+		concat!(
+			"\tpub fn simplify_targets() -> &'static [WholeUnit] {{\n",
+			"\t\treturn &["
+		)
+	).unwrap();
+
+	for u in units {
+		if u.get("simplify").is_some_and(|x| x.as_bool().unwrap()) {
+			writeln!(file,
+				"\t\t\tWholeUnit::{},",
+				u["enum_name"].as_str().unwrap()
+			).unwrap();
+		}
+	}
+
+	writeln!(file, "\t\t]\n\t}}\n").unwrap();
+
+
+	// Units we may give a better prefix
+	writeln!(file,
+		concat!(
+			"\tpub fn reprefix(&self) -> bool {{\n",
+			"\t\tmatch self {{"
+		)
+	).unwrap();
+
+	for u in units {
+		if u.get("reprefix").is_some_and(|x| x.as_bool().unwrap()) {
+			writeln!(file,
+				"\t\t\tWholeUnit::{} => true,",
+				u["enum_name"].as_str().unwrap()
+			).unwrap();
+		}
+	}
+
+	writeln!(file, "\t\t\t_ => false\n\t\t}}\n\t}}\n").unwrap();
+
+
+	// Prefixes this unit may be printed with.
+	// `long` is the long name the unit was written with, see `FreeUnit`.
+	// Short prefixes go with the first string, since that's the one we print.
+	writeln!(file,
+		concat!(
+			"\tpub fn prefixes(&self, long: Option<&str>) -> &'static [Prefix] {{\n",
+			"\t\tmatch (self, long) {{"
+		)
+	).unwrap();
+
+	for u in units {
+		let strings: Vec<&Vec<Value>> = u["strings"].as_array().unwrap().iter()
+			.map(|s| s.as_array().unwrap())
+			.collect();
+
+		// A name may be listed more than once, like `B` with decimal and binary prefixes.
+		let mut names: Vec<&str> = Vec::new();
+		for s in &strings {
+			let n = s[0].as_str().unwrap();
+			if !names.contains(&n) { names.push(n); }
+		}
+
+		for n in names {
+			let ps: Vec<&str> = strings.iter()
+				.filter(|s| s[0].as_str().unwrap() == n)
+				.flat_map(|s| s[1..].iter().map(|p| p.as_str().unwrap()))
+				.collect();
+
+			// Short prefixes are at most two letters long, see `write_freeunit_from_string`.
+			let (short, long): (Vec<&str>, Vec<&str>) = ps.iter().partition(|p| p.len() <= 2);
+			let list = |ps: Vec<&str>| {
+				ps.iter().map(|p| format!("str_to_prefix!(\"{p}\")")).collect::<Vec<_>>().join(", ")
+			};
+
+			if n == strings[0][0].as_str().unwrap() && !short.is_empty() {
 				writeln!(file,
-					"\t\t\tWholeUnit::{} => true,",
-					u["enum_name"].as_str().unwrap()
+					"\t\t\t(WholeUnit::{e}, None) => &[{p}],",
+					e = u["enum_name"].as_str().unwrap(),
+					p = list(short.clone())
+				).unwrap();
+			}
+
+			// Long names may also go without a prefix
+			if !long.is_empty() {
+				let l = ps.iter().copied().filter(|p| p.len() > 2 || p.is_empty()).collect();
+				writeln!(file,
+					"\t\t\t(WholeUnit::{e}, Some(\"{n}\")) => &[{l}],",
+					e = u["enum_name"].as_str().unwrap(),
+					n = escape(n),
+					l = list(l)
 				).unwrap();
 			}
 		}
 	}
 
-	writeln!(file, "\t\t\t_ => false\n\t\t}}\n\t}}\n}}").unwrap();
+	writeln!(file, "\t\t\t_ => &[]\n\t\t}}\n\t}}\n").unwrap();
+
+
+	// Currencies with exchange rates
+	writeln!(file,
+		concat!(
+			"\tpub fn rate_units() -> &'static [WholeUnit] {{\n",
+			"\t\treturn &["
+		)
+	).unwrap();
+
+	for u in units {
+		if u.as_table().unwrap().get("base_value_type").is_some_and(|x| x.as_str() == Some("rate")) {
+			writeln!(file,
+				"\t\t\tWholeUnit::{},",
+				u["enum_name"].as_str().unwrap()
+			).unwrap();
+		}
+	}
+
+	writeln!(file, "\t\t]\n\t}}\n").unwrap();
+
+
+	// Every unit, in the order they're defined
+	writeln!(file,
+		concat!(
+			"\tpub fn all() -> &'static [WholeUnit] {{\n",
+			"\t\treturn &["
+		)
+	).unwrap();
+
+	for u in units {
+		writeln!(file,
+			"\t\t\tWholeUnit::{},",
+			u["enum_name"].as_str().unwrap()
+		).unwrap();
+	}
+
+	writeln!(file, "\t\t]\n\t}}\n}}").unwrap();
 }
 
 
@@ -79,7 +247,7 @@ fn write_wholeunit_base_factor(mut file: &File, units: &Vec<Value>) {
 	writeln!(file,
 		concat!(
 			"impl WholeUnit {{\n",
-			"\tfn base_factor(&self) -> Option<Quantity> {{\n",
+			"\tfn base_factor(&self, rates: Option<&Rates>) -> Option<Quantity> {{\n",
 			"\t\tmatch self {{"
 		)
 	).unwrap();
@@ -99,6 +267,16 @@ fn write_wholeunit_base_factor(mut file: &File, units: &Vec<Value>) {
 		}
 
 
+		// Exchange rates are loaded at runtime, see `currency.rs`.
+		if u["base_value_type"].as_str().unwrap() == "rate" {
+			writeln!(file,
+				"\t\t\tWholeUnit::{e} => rate_factor(rates, WholeUnit::{e}, WholeUnit::{b}),",
+				e = u["enum_name"].as_str().unwrap(),
+				b = u["base_units"].as_array().unwrap()[0].as_table().unwrap()["u"].as_str().unwrap()
+			).unwrap();
+			continue
+		}
+
 		writeln!(file,
 			"\t\t\tWholeUnit::{} => Some(Quantity{{",
 			u["enum_name"].as_str().unwrap()
@@ -107,14 +285,14 @@ fn write_wholeunit_base_factor(mut file: &File, units: &Vec<Value>) {
 		match u["base_value_type"].as_str().unwrap() {
 			"exact" => {
 				writeln!(file,
-					"\t\t\t\tscalar: Scalar::new_rational_from_string(\"{}\").unwrap(),",
+					"\t\t\t\tscalar: Scalar::new_rational_from_string(\"{}\").unwrap()",
 					u["base_value"].as_str().unwrap(),
 				).unwrap();
 			},
 
 			"fract" => {
 				writeln!(file,
-					"\t\t\t\tscalar: Scalar::new_rational_from_frac({}, {}).unwrap(),",
+					"\t\t\t\tscalar: Scalar::new_rational_from_frac({}, {}).unwrap()",
 					u["base_value"].as_array().unwrap()[0].as_integer().unwrap(),
 					u["base_value"].as_array().unwrap()[1].as_integer().unwrap(),
 				).unwrap();
@@ -122,7 +300,7 @@ fn write_wholeunit_base_factor(mut file: &File, units: &Vec<Value>) {
 
 			"approx" => {
 				writeln!(file,
-					"\t\t\t\tscalar: Scalar::new_float_from_string(\"{}\").unwrap(),",
+					"\t\t\t\tscalar: Scalar::new_float_from_string(\"{}\").unwrap()",
 					u["base_value"].as_str().unwrap(),
 				).unwrap();
 			},
@@ -130,17 +308,28 @@ fn write_wholeunit_base_factor(mut file: &File, units: &Vec<Value>) {
 			_ => panic!()
 		};
 
+		if let Some(cs) = u.get("base_constants") {
+			for c in cs.as_array().unwrap() {
+				writeln!(file,
+					"\t\t\t\t\t* constant_scalar(Constant::{c}).pow(Scalar::new_rational({p}f64).unwrap())",
+					c = c.as_table().unwrap()["c"].as_str().unwrap(),
+					p = c.as_table().unwrap()["p"].as_integer().unwrap(),
+				).unwrap();
+			}
+		}
+		writeln!(file, "\t\t\t\t,").unwrap();
+
 		writeln!(file,
 			concat!(
 				"\t\t\t\tunit: Unit::from_array(&[\n",
-				"\t\t\t\t\t(FreeUnit{{whole: WholeUnit::{}, prefix: Prefix::None}}, Scalar::new_rational(-1f64).unwrap()),",
+				"\t\t\t\t\t(FreeUnit{{whole: WholeUnit::{}, prefix: Prefix::None, long: None}}, Scalar::new_rational(-1f64).unwrap()),",
 			),
 			u["enum_name"].as_str().unwrap()
 		).unwrap();
 
 		for b in u["base_units"].as_array().unwrap() {
 			writeln!(file,
-				"\t\t\t\t\t(FreeUnit{{whole: WholeUnit::{u}, prefix: Prefix::None}}, Scalar::new_rational({p}f64).unwrap()),",
+				"\t\t\t\t\t(FreeUnit{{whole: WholeUnit::{u}, prefix: Prefix::None, long: None}}, Scalar::new_rational({p}f64).unwrap()),",
 				u = b.as_table().unwrap()["u"].as_str().unwrap(),
 				p = b.as_table().unwrap()["p"].as_integer().unwrap(),
 			).unwrap();
@@ -148,7 +337,8 @@ fn write_wholeunit_base_factor(mut file: &File, units: &Vec<Value>) {
 
 		writeln!(file,
 			concat!(
-				"\t\t\t\t])\n",
+				"\t\t\t\t]),\n",
+				"\t\t\t\tuncertainty: None\n",
 				"\t\t\t}}),"
 			),
 		).unwrap();
@@ -175,16 +365,21 @@ fn write_freeunit_from_string(mut file: &File, units: &Vec<Value>) {
 
 			if s.as_array().unwrap().len() == 1 {
 				writeln!(file,
-					"\t\t\"{}\" => Some(FreeUnit{{whole: WholeUnit::{}, prefix: Prefix::None}}),",
-					s.as_array().unwrap()[0].as_str().unwrap(),
+					"\t\t\"{}\" => Some(FreeUnit{{whole: WholeUnit::{}, prefix: Prefix::None, long: None}}),",
+					escape(s.as_array().unwrap()[0].as_str().unwrap()),
 					u["enum_name"].as_str().unwrap()
 				).unwrap();
 			} else {
 				for p in &s.as_array().unwrap()[1..] {
+					let p = p.as_str().unwrap();
+					let n = escape(s.as_array().unwrap()[0].as_str().unwrap());
+
+					// Short prefixes are at most two letters long (like `da` and `Ki`).
+					// Units with long prefixes remember their name, so we can print them as written.
+					let l = if p.len() > 2 { format!("Some(\"{n}\")") } else { String::from("None") };
+
 					writeln!(file,
-						"\t\t\"{p}{u}\" => Some(FreeUnit{{whole: WholeUnit::{e}, prefix: str_to_prefix!(\"{p}\")}}),",
-						u = s.as_array().unwrap()[0].as_str().unwrap(),
-						p = p.as_str().unwrap(),
+						"\t\t\"{p}{n}\" => Some(FreeUnit{{whole: WholeUnit::{e}, prefix: str_to_prefix!(\"{p}\"), long: {l}}}),",
 						e = u["enum_name"].as_str().unwrap()
 					).unwrap();
 				}
@@ -193,12 +388,42 @@ fn write_freeunit_from_string(mut file: &File, units: &Vec<Value>) {
 
 		}
 
-		writeln!(file, "").unwrap();
+		writeln!(file).unwrap();
 	}
 
 	writeln!(file, "\t\t_ => None\n\t}}\n}}").unwrap();
 }
 
+/// Create all_unit_strings().
+/// Should only be run once.
+fn write_all_unit_strings(mut file: &File, units: &Vec<Value>) {
+	writeln!(file,
+		concat!(
+			"// Every string that may be used as a unit, without prefixes.\n",
+			"pub fn all_unit_strings() -> &'static [&'static str] {{\n",
+			"\treturn &["
+		),
+	).unwrap();
+
+	for u in units {
+		for s in u["strings"].as_array().unwrap() {
+			let s = s.as_array().unwrap();
+
+			// Skip strings that may only be used with a prefix
+			if s.len() != 1 && !s[1..].iter().any(|p| p.as_str().unwrap() == "") {
+				continue;
+			}
+
+			writeln!(file,
+				"\t\t\"{}\",",
+				escape(s[0].as_str().unwrap())
+			).unwrap();
+		}
+	}
+
+	writeln!(file, "\t]\n}}").unwrap();
+}
+
 pub fn write(target: &Path) {
 	let units = include_str!("units.toml").parse::<Table>().unwrap();
 	let toml::Value::Array(units) = &units["unit"] else {panic!()};
@@ -218,4 +443,7 @@ pub fn write(target: &Path) {
 	writeln!(file, "\n\n").unwrap();
 
 	write_freeunit_from_string(&file, units);
+	writeln!(file, "\n\n").unwrap();
+
+	write_all_unit_strings(&file, units);
 }
@@ -0,0 +1,254 @@
+//! A simple interface for embedding daisy in other programs.
+//!
+//! ```
+//! use daisycalc::Calculator;
+//!
+//! let mut calc = Calculator::new();
+//! calc.define_variable("radius", "2 m").unwrap();
+//!
+//! let v = calc.eval("pi * radius^2").unwrap();
+//! let q = v.as_quantity().unwrap();
+//! assert_eq!(calc.display(&v), "12.566 m²");
+//! assert!((q.to_f64() - 12.566).abs() < 0.001);
+//! ```
+
+use std::fmt;
+
+use crate::parser;
+use crate::parser::Expression;
+use crate::parser::LineLocation;
+use crate::quantity::Quantity;
+use crate::quantity::Unit;
+use crate::context::Context;
+use crate::context::NativeFunction;
+use crate::errors::DaisyError;
+use crate::evaluate::evaluate;
+use crate::evaluate::evaluate_variable;
+use crate::evaluate::check_function;
+
+
+/// The result of an evaluation.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum Value {
+	Quantity(Quantity),
+	Boolean(bool),
+	Tuple(Vec<Value>)
+}
+
+impl Value {
+	pub(crate) fn from_expression(e: &Expression) -> Option<Value> {
+		match e {
+			Expression::Quantity(_, q) => Some(Value::Quantity(q.clone())),
+			Expression::Boolean(_, b) => Some(Value::Boolean(*b)),
+			Expression::Tuple(_, v) => {
+				let mut o = Vec::with_capacity(v.len());
+				for x in v { o.push(Value::from_expression(x)?); }
+				Some(Value::Tuple(o))
+			},
+			_ => None
+		}
+	}
+
+	pub(crate) fn to_expression(&self) -> Expression {
+		match self {
+			Value::Quantity(q) => Expression::Quantity(LineLocation::new_zero(), q.clone()),
+			Value::Boolean(b) => Expression::Boolean(LineLocation::new_zero(), *b),
+			Value::Tuple(v) => Expression::Tuple(
+				LineLocation::new_zero(),
+				v.iter().map(|x| x.to_expression()).collect()
+			)
+		}
+	}
+
+	/// Returns the quantity in this value, if it is one.
+	pub fn as_quantity(&self) -> Option<&Quantity> {
+		match self {
+			Value::Quantity(q) => Some(q),
+			_ => None
+		}
+	}
+
+	/// Returns this value as a bool, if it is one.
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Value::Boolean(b) => Some(*b),
+			_ => None
+		}
+	}
+
+	/// Returns the elements of this value, if it is a tuple.
+	pub fn as_tuple(&self) -> Option<&[Value]> {
+		match self {
+			Value::Tuple(v) => Some(v),
+			_ => None
+		}
+	}
+}
+
+
+
+/// An error produced while parsing or evaluating input.
+#[derive(Debug)]
+pub struct Error {
+	/// What part of the input caused this error.
+	/// This is zero if the error isn't tied to a position.
+	pub location: LineLocation,
+
+	/// What went wrong.
+	pub error: DaisyError
+}
+
+impl Error {
+	fn new((location, error): (LineLocation, DaisyError)) -> Error {
+		return Error { location, error };
+	}
+
+	/// A human-readable description of this error, without formatting tags.
+	pub fn message(&self) -> String {
+		return self.error.text().plain();
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.message())
+	}
+}
+
+impl std::error::Error for Error {}
+
+
+
+/// A calculator with its own variables and functions.
+#[derive(Debug)]
+pub struct Calculator {
+	context: Context
+}
+
+impl Calculator {
+	pub fn new() -> Calculator {
+		return Calculator { context: Context::new() };
+	}
+
+	pub fn context(&self) -> &Context { &self.context }
+	pub fn context_mut(&mut self) -> &mut Context { &mut self.context }
+
+	/// Evaluate an expression.
+	/// The result is saved, and may be referenced as `ans`,
+	/// or by number as `$1`, `ans1` or `out[1]`.
+	pub fn eval(&mut self, s: &str) -> Result<Value, Error> {
+		let g = parser::parse(&self.context, &s.to_string()).map_err(Error::new)?;
+		let g = evaluate(&mut self.context, &g).map_err(Error::new)?;
+
+		let Some(v) = Value::from_expression(&g) else {
+			return Err(Error {
+				location: g.get_linelocation(),
+				error: DaisyError::EvaluationError
+			});
+		};
+
+		self.context.push_hist(s.to_string(), g);
+		return Ok(v);
+	}
+
+	/// Evaluate `s` and save the result in variable `name`,
+	/// as if the user typed `name = s`.
+	///
+	/// Returns `None` if the new variable is floating,
+	/// that is, if it depends on a variable that isn't defined yet.
+	pub fn define_variable(&mut self, name: &str, s: &str) -> Result<Option<Value>, Error> {
+		if !self.context.valid_varible(name) {
+			return Err(Error { location: LineLocation::new_zero(), error: DaisyError::BadVariable });
+		}
+
+		let g = parser::parse(&self.context, &s.to_string()).map_err(Error::new)?;
+		let g = evaluate_variable(&mut self.context, &name.to_string(), &g).map_err(Error::new)?;
+
+		let v = Value::from_expression(&g);
+		self.context.push_variable(name.to_string(), g).unwrap();
+		return Ok(v);
+	}
+
+	/// Set variable `name` to `v`.
+	pub fn set_variable(&mut self, name: &str, v: Value) -> Result<(), Error> {
+		if !self.context.valid_varible(name) {
+			return Err(Error { location: LineLocation::new_zero(), error: DaisyError::BadVariable });
+		}
+
+		self.context.push_variable(name.to_string(), v.to_expression()).unwrap();
+		return Ok(());
+	}
+
+	/// Get the value of a variable, if it is defined.
+	pub fn get_variable(&self, name: &str) -> Option<Value> {
+		if !self.context.is_varible(name) { return None; }
+		let e = self.context.get_variable(&name.to_string())?;
+		return Value::from_expression(&e);
+	}
+
+	/// Define a function, as if the user typed `name(args) = body`.
+	pub fn define_function(&mut self, name: &str, args: &[&str], body: &str) -> Result<(), Error> {
+		if !self.context.valid_function(name) {
+			return Err(Error { location: LineLocation::new_zero(), error: DaisyError::BadFunction });
+		}
+
+		for a in args {
+			if !self.context.valid_varible(a) {
+				return Err(Error { location: LineLocation::new_zero(), error: DaisyError::BadVariable });
+			}
+		}
+
+		let name = name.to_string();
+		let args: Vec<String> = args.iter().map(|x| x.to_string()).collect();
+
+		let g = parser::parse_function(&mut self.context, &name, &args, &body.to_string()).map_err(Error::new)?;
+		check_function(&mut self.context, &name, &args, &g).map_err(Error::new)?;
+		self.context.push_function(name, args, g).unwrap();
+
+		return Ok(());
+	}
+
+	/// Register a function implemented in Rust.
+	///
+	/// `f` is called with exactly `arity` arguments,
+	/// all of which have been fully evaluated.
+	pub fn register_function<F>(&mut self, name: &str, arity: usize, f: F) -> Result<(), Error>
+	where F: Fn(&[Quantity]) -> Result<Quantity, DaisyError> + 'static {
+		return self.register_native_function(name, NativeFunction::new(arity, f));
+	}
+
+	/// Register a function implemented in Rust.
+	/// Use this with `NativeFunction::new_with_units` to
+	/// have daisy check and convert argument units.
+	pub fn register_native_function(&mut self, name: &str, f: NativeFunction) -> Result<(), Error> {
+		let r = self.context.push_native_function(name.to_string(), f);
+
+		if r.is_err() {
+			return Err(Error { location: LineLocation::new_zero(), error: DaisyError::BadFunction });
+		}
+
+		return Ok(());
+	}
+
+	/// Evaluate `s` and return its unit, ignoring its value.
+	/// For example, `unit("km/h")` is the unit `km/h`.
+	pub fn unit(&mut self, s: &str) -> Result<Unit, Error> {
+		let g = parser::parse(&self.context, &s.to_string()).map_err(Error::new)?;
+		let g = evaluate(&mut self.context, &g).map_err(Error::new)?;
+
+		let Expression::Quantity(_, q) = g else {
+			return Err(Error {
+				location: g.get_linelocation(),
+				error: DaisyError::EvaluationError
+			});
+		};
+
+		return Ok(q.unit);
+	}
+
+	/// Format a value the same way the daisy prompt does.
+	pub fn display(&self, v: &Value) -> String {
+		return v.to_expression().display_outer(&self.context);
+	}
+}
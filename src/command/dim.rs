@@ -0,0 +1,135 @@
+use crate::context::Context;
+use crate::parser::Expression;
+use crate::parser::Statement;
+use crate::parser::parse_statement;
+use crate::evaluate::evaluate;
+use crate::quantity::FreeUnit;
+use crate::quantity::WholeUnit;
+use crate::quantity::Unit;
+use crate::quantity::Scalar;
+use crate::formattedtext::FormattedText;
+
+
+// The seven SI base dimensions, in the order we print them.
+// (unit, symbol, ascii symbol)
+const SI_BASES: [(WholeUnit, &str, &str); 7] = [
+	(WholeUnit::Meter, "L", "L"),
+	(WholeUnit::Gram, "M", "M"),
+	(WholeUnit::Second, "T", "T"),
+	(WholeUnit::Ampere, "I", "I"),
+	(WholeUnit::Kelvin, "Θ", "Th"),
+	(WholeUnit::Mole, "N", "N"),
+	(WholeUnit::Candela, "J", "J"),
+];
+
+// Write the dimension of `u`, which must only contain base units.
+// SI dimensions come first (like L¹M¹T⁻²), then any other base units.
+fn dimension_string(context: &Context, u: &Unit) -> String {
+	let sup = context.config.enable_super_powers;
+
+	let power = |p: &Scalar| -> String {
+		if !sup || !p.is_int() || p.to_string().contains("e") {
+			return format!("^{}", p.to_string());
+		}
+
+		return p.to_string().chars().map(|c| match c {
+			'-' => '⁻',
+			'0' => '⁰',
+			'1' => '¹',
+			'2' => '²',
+			'3' => '³',
+			'4' => '⁴',
+			'5' => '⁵',
+			'6' => '⁶',
+			'7' => '⁷',
+			'8' => '⁸',
+			'9' => '⁹',
+			_ => unreachable!()
+		}).collect();
+	};
+
+	let mut v: Vec<String> = Vec::new();
+	for (w, s, a) in SI_BASES {
+		let Some(p) = u.get_val().get(&FreeUnit::from_whole(w)) else { continue };
+		let s = if context.config.enable_unicode { s } else { a };
+		v.push(format!("{s}{}", power(p)));
+	}
+	// Superscripts are easy to read without spaces
+	let mut t = v.join(if sup { "" } else { " " });
+
+	let mut v: Vec<String> = u.get_val().iter()
+		.filter(|(f, _)| !SI_BASES.iter().any(|(w, _, _)| f.whole() == *w))
+		.map(|(f, p)| {
+			let s = if context.config.enable_unicode { f.to_string() } else { f.to_ascii_string() };
+			format!("{s}{}", power(p))
+		})
+		.collect();
+	v.sort();
+	for s in v {
+		if t.len() != 0 { t.push(' '); }
+		t.push_str(&s);
+	}
+
+	if t.len() == 0 { return String::from("1"); }
+	return t;
+}
+
+
+// Show the dimension of the expression in `s`,
+// what kind of quantity it is, and which units have the same dimension.
+pub fn dim(context: &mut Context, s: &str) -> FormattedText {
+	let s = s.trim().to_string();
+	if s.len() == 0 {
+		return FormattedText::new("[c]dim[n] [t]takes an expression.[n]\n\n".to_string());
+	}
+
+	let g = match parse_statement(context, &s) {
+		Ok(Statement::Expression(g)) => g,
+		Ok(_) => {
+			return FormattedText::new("[c]dim[n] [t]takes an expression, not a definition.[n]\n\n".to_string());
+		},
+		Err((_, e)) => {
+			return FormattedText::new(format!("\n  {}\n\n", e.text().to_string()));
+		}
+	};
+
+	let q = match evaluate(context, &g) {
+		Ok(Expression::Quantity(_, q)) => q,
+		Ok(_) => {
+			return FormattedText::new("[c]dim[n] [t]takes a single number.[n]\n\n".to_string());
+		},
+		Err((_, e)) => {
+			return FormattedText::new(format!("\n  {}\n\n", e.text().to_string()));
+		}
+	};
+
+	let b = q.unit.to_base(context.get_rates()).unit;
+
+	let kind = b.kind(context.get_rates());
+
+	let units: Vec<String> = WholeUnit::all().iter()
+		.filter(|w| Unit::from_free(FreeUnit::from_whole(**w)).to_base(context.get_rates()).unit == b)
+		.map(|w| {
+			let f = FreeUnit::from_whole(*w);
+			if context.config.enable_unicode { f.to_string() } else { f.to_ascii_string() }
+		})
+		.collect();
+
+	let mut t = FormattedText::new(format!(
+		"\n  Dimension: [c]{}[n]\n",
+		dimension_string(context, &b)
+	));
+
+	if let Some(k) = kind {
+		t.push(&format!("  Kind:      [c]{k}[n]\n"));
+	}
+
+	if units.len() == 0 {
+		t.push("  Units:     [i]none[n]\n");
+	} else {
+		t.push(&format!("  Units:     [c]{}[n]\n", units.join(", ")));
+	}
+
+	t.push("\n\n");
+	return t;
+}
@@ -1,8 +1,12 @@
 use crate::context::Context;
+use crate::context::NativeFunction;
 use crate::parser::Constant;
 use crate::parser::substitute;
+use crate::quantity::FreeUnit;
 use crate::formattedtext::FormattedText;
 
+mod dim;
+
 pub fn is_command(
 	s: &String
 ) -> bool {
@@ -16,12 +20,34 @@ pub fn is_command(
 		| "vars"
 		| "consts" | "constants"
 		| "del" | "delete"
+		| "deps"
+		| "history"
 		| "flags"
+		| "rates"
+		| "dim"
 		=> true,
 		_ => false
 	}
 }
 
+// Every command name, for completion.
+// `quit` is handled by the prompt, not here.
+pub fn all_commands() -> &'static [&'static str] {
+	return &[
+		"help", "clear", "quit",
+		"ops", "operators",
+		"fns", "functions",
+		"vars",
+		"consts", "constants",
+		"del", "delete",
+		"deps",
+		"history",
+		"flags",
+		"rates",
+		"dim"
+	];
+}
+
 #[inline(always)]
 fn greeter() -> FormattedText {
 	return FormattedText::new(
@@ -43,10 +69,58 @@ fn greeter() -> FormattedText {
 }
 
 
+// Draws the dependency tree of variable `s`.
+// `prefix` is drawn before each line below the first.
+fn dependency_tree(context: &Context, t: &mut FormattedText, s: &String, prefix: String) {
+	let d = context.get_dependencies(s);
+
+	let mut i = 0;
+	while i < d.len() {
+		let last = i == d.len() - 1;
+		let v = &d[i];
+
+		let note = if context.is_varible(v) {
+			if context.is_floating(v) { "" } else { " [i](value)[n]" }
+		} else { " [e](not defined)[n]" };
+
+		t.push(&format!(
+			"  {prefix}{}[c]{v}[n]{note}\n",
+			if last { "└─ " } else { "├─ " }
+		));
+
+		dependency_tree(
+			context, t, v,
+			format!("{prefix}{}", if last { "   " } else { "│  " })
+		);
+
+		i += 1;
+	}
+}
+
+// Replace box-drawing characters with plain ASCII.
+fn ascii_borders(s: &str) -> String {
+	return s.chars().map(|c| match c {
+		'═' => '=',
+		'─' => '-',
+		'╞' | '╡' | '╪' | '│' | '├' => '|',
+		'└' => '`',
+		_ => c
+	}).collect();
+}
+
 #[inline(always)]
 pub fn do_command(
 	context: &mut Context,
 	s: &String,
+) -> FormattedText {
+	let t = run_command(context, s);
+	if context.config.enable_unicode { return t; }
+	return FormattedText::new(ascii_borders(&t.to_string()));
+}
+
+fn run_command(
+	context: &mut Context,
+	s: &String,
 ) -> FormattedText {
 	let args: Vec<&str> = s.split(" ").collect();
 	let first = args[0];
@@ -61,21 +135,28 @@ pub fn do_command(
 					"scientific calculator.\n",
 					"\n",
 					" - Use Up/Down arrows to navigate history.\n",
+					" - Use Tab to complete names.\n",
+					" - Use Ctrl-R to search history.\n",
 					" - Use Ctrl-C or Ctrl-D to quit.\n",
 					" - Use [c]ans[n] to reference the last result.\n",
+					" - Use [c]$3[n], [c]ans3[n] or [c]out[3][n] to reference result 3.\n",
 					" - Use [c]var = 1337[n] to define varibles.\n",
 					"\n",
 					"╞═══════════════ [t]Commands[n] ═══════════════╡\n",
-					"      [c]help[n]   Show this help\n",
-					"      [c]flags[n]  Show command-line options\n",
-					"      [c]clear[n]  Clear the terminal\n",
-					"      [c]quit[n]   Exit daisy\n",
-					//"      [c]units[n]  List available units\n",
-					"      [c]consts[n] List built-in constants\n",
-					"      [c]ops[n]    List built-in operators\n",
-					"      [c]fns[n]    List built-in functions\n",
-					"      [c]vars[n]   List user-defined variables\n",
-					"      [c]del[n]    Delete a variable\n",
+					"      [c]help[n]    Show this help\n",
+					"      [c]flags[n]   Show command-line options\n",
+					"      [c]clear[n]   Clear the terminal\n",
+					"      [c]quit[n]    Exit daisy\n",
+					//"      [c]units[n]   List available units\n",
+					"      [c]consts[n]  List built-in constants\n",
+					"      [c]ops[n]     List built-in operators\n",
+					"      [c]fns[n]     List built-in functions\n",
+					"      [c]vars[n]    List user-defined variables\n",
+					"      [c]del[n]     Delete a variable\n",
+					"      [c]deps[n]    Show what a variable depends on\n",
+					"      [c]dim[n]     Show the dimension of an expression\n",
+					"      [c]history[n] List previous results\n",
+					"      [c]rates[n]   Show exchange rates\n",
 					"\n\n",
 				)
 			);
@@ -93,12 +174,26 @@ pub fn do_command(
 					"  [c]--help[n]        Show help\n",
 					"  [c]--version[n]     Show version\n",
 					"  [c]--info[n]        Show system information\n",
-					"  [c]--256color[n]    Use full color support (default)\n",
+					"  [c]--256color[n]    Use full color support\n",
 					"  [c]--8color[n]      Use reduced colors (ANSI, no styling)\n",
 					"  [c]--nocolor[n]     Do not use colors and styling\n",
+					"\n",
+					"  Colors are detected from [c]NO_COLOR[n], [c]COLORTERM[n], [c]TERM[n] and terminfo,\n",
+					"  and are disabled if output isn't a terminal.\n",
+					"\n",
 					"  [c]--nosub[n]       Disable inline substitution\n",
 					"  [c]--nosuper[n]     Disable superscript powers\n",
 					"  [c]--nooneover[n]   Disable \"one-over\" fractions as -1 power\n",
+					"  [c]--nosimplify[n]  Don't pick the best unit for results\n",
+					"  [c]--ascii[n]       Only print ASCII (default if locale isn't UTF-8)\n",
+					"  [c]--unicode[n]     Print unicode, even if locale isn't UTF-8\n",
+					"\n",
+					"  [c]--rates=FILE[n]  Read exchange rates from [c]FILE[n]\n",
+					"\n",
+					"  By default, exchange rates are read from [c]$XDG_CONFIG_HOME/daisy/rates.csv[n]\n",
+					"  or [c]~/.config/daisy/rates.csv[n]. Each line of this file looks like [c]EUR,1.08[n],\n",
+					"  the price of one unit in US dollars. A line like [c]updated,2024-05-01[n]\n",
+					"  says when these rates were updated.\n",
 					"\n\n"
 				).to_string()
 			);
@@ -109,8 +204,10 @@ pub fn do_command(
 		},
 
 		"ops" | "operators" => {
+			// Unicode spellings are only listed if we can print them
+			let u = context.config.enable_unicode;
 			return FormattedText::new(
-				concat!(
+				format!(concat!(
 					"\n",
 					"Operators, sorted by priority (high to low).\n",
 					"High-piority operators are applied first.\n\n",
@@ -118,22 +215,39 @@ pub fn do_command(
 					"  function             [c]sin, cos, etc[n]\n",
 					"  factorial            [c]![n]\n",
 					"  powers               [c]^, **[n]\n",
-					"  implicit multiply    [c]3π, 3(2+1), etc[n]\n",
-					"  square root          [c]sqrt, rt, √[n]\n",
+					"  implicit multiply    [c]3{pi}, 3(2+1), etc[n]\n",
+					"  square root          [c]sqrt, rt{sqrt}[n]\n",
 					"  negate               [c]-3, -(1 + 2)[n]\n",
 					"  modulo (short)       [c]%[n]\n",
-					"  multiply, divide     [c]*, /, ×, ÷[n]\n",
+					"  multiply, divide     [c]*, /{mul}[n]\n",
+					"  uncertainty          [c]+/-{pm}[n]\n",
 					"  add, subtract        [c]+, -[n]\n",
 					"  unit conversion      [c]to[n]\n",
 					"  division (long)      [c]per[n]\n",
 					"  modulo (long)        [c]mod[n]\n",
+					"  comparison           [c]<, >, <=, >=, ==, !=[n]\n",
+					"  logical not          [c]not[n]\n",
+					"  logical and          [c]and, &&[n]\n",
+					"  logical or           [c]or, ||[n]\n",
+					"\n",
+					"Uncertainties are treated as independent, even if they\n",
+					"come from the same value: [c]x - x[n] is [c]0 {pm_sym} {sqrt_2}[n]\n",
+					"when [c]x = 1 {pm_sym} 1[n]. Rounding functions like [c]floor[n]\n",
+					"are flat between jumps, so their results are exact.\n",
 					"\n\n"
-				).to_string()
+				),
+					pi = if u { "π" } else { "pi" },
+					sqrt = if u { ", √" } else { "" },
+					mul = if u { ", ×, ÷" } else { "" },
+					pm = if u { ", ±" } else { "" },
+					pm_sym = if u { "±" } else { "+/-" },
+					sqrt_2 = if u { "√2" } else { "sqrt(2)" }
+				)
 			);
 		},
 
 		"fns" | "functions" => {
-			return FormattedText::new(
+			let mut t = FormattedText::new(
 				concat!(
 					"\n╞═══════ [t]Function[n] ═══════╪══════ [t]Syntax[n] ══════╡\n",
 					"  absolute value           [c]abs[n]\n",
@@ -154,9 +268,35 @@ pub fn do_command(
 					"\n",
 					"  convert to base unit     [c]tobase[n]\n",
 					"  remove units             [c]nounit[n]\n",
+					"\n",
+					"  a if cond, else b        [c]if(cond, a, b)[n]\n",
 					"\n\n"
 				).to_string()
 			);
+
+			// Functions provided by the program daisy is embedded in
+			let n = context.get_native_functions();
+			if n.len() != 0 {
+				let mut v: Vec<(&String, &NativeFunction)> = n.iter().collect();
+				v.sort_by(|a, b| a.0.cmp(b.0));
+
+				t.push("╞═══ [t]Host Functions[n] ═══╡\n");
+				for (key, f) in v {
+					let args = f.units().iter()
+						.map(|u| match u {
+							None => String::from("_"),
+							Some(u) if u.unitless() => String::from("scalar"),
+							Some(u) => u.display(context)
+						})
+						.collect::<Vec<String>>()
+						.join(", ");
+
+					t.push(&format!("  {key}([c]{args}[n])\n"));
+				}
+				t.push("\n\n");
+			}
+
+			return t;
 		},
 
 		"vars" => {
@@ -216,6 +356,37 @@ pub fn do_command(
 			return t;
 		},
 
+		"history" => {
+			let h = context.get_history();
+
+			if h.len() == 0 {
+				return FormattedText::new(
+					"You have not evaluated anything yet\n\n".to_string()
+				);
+			}
+
+			let mut t = FormattedText::new(
+				"\n╞═══ [t]History[n] ═══╡\n".to_string()
+			);
+
+			let longest_n = format!("{}", h.len()).len();
+			let longest = h.iter().map(|(s, _)| s.chars().count()).max().unwrap();
+
+			for (i, (s, e)) in h.iter().enumerate() {
+				let n = format!("{}", i + 1);
+
+				t.push(&format!(
+					"  [r]${n}[n]{} {s}{} = [c]{v}[n]\n",
+					" ".repeat(longest_n - n.len()),
+					" ".repeat(longest - s.chars().count()),
+					v = e.display_outer(context),
+				));
+			}
+
+			t.push("\n\n");
+			return t;
+		},
+
 		"consts" | "constants" => {
 			let a = Constant::all_consts();
 
@@ -226,6 +397,13 @@ pub fn do_command(
 
 			for c in a {
 				let Some(p) = c.pretty_name() else { continue };
+				let u = context.config.enable_unicode;
+
+				let p = if u || p.is_ascii() { p.to_string() } else { c.to_ascii_string() };
+				let s: Vec<&str> = c.source_strings().iter()
+					.filter(|x| u || x.is_ascii())
+					.cloned()
+					.collect();
 
 				// If you subtract with overflow here,
 				// your padding length is too short.
@@ -233,7 +411,7 @@ pub fn do_command(
 
 				t.push(&format!(
 					"  {p}{padding}: [c]{s}[n]",
-					s = c.source_strings().join(", "),
+					s = s.join(", "),
 				));
 
 				t.push(&"\n");
@@ -268,6 +446,77 @@ pub fn do_command(
 			};
 		},
 
+		"deps" => {
+			if args.len() != 2 {
+				return FormattedText::new(
+					format!(
+						"[c]{first}[n] [t]takes exactly one argument.[n]\n\n",
+					)
+				);
+			}
+
+			let v = args[1].to_string();
+			let v = substitute(context, &v);
+
+			if !context.get_variables().contains_key(&v) {
+				return FormattedText::new(
+					format!(
+						"[c]{v}[n] [t]isn't a variable.[n]\n\n",
+					)
+				);
+			}
+
+			if !context.is_floating(&v) {
+				return FormattedText::new(
+					format!(
+						"[c]{v}[n] [t]is a value, and doesn't depend on any variables.[n]\n\n",
+					)
+				);
+			}
+
+			let mut t = FormattedText::new(format!(
+				"\n╞═══ [t]Dependencies[n] ═══╡\n  [c]{v}[n]\n"
+			));
+			dependency_tree(context, &mut t, &v, String::new());
+			t.push("\n\n");
+			return t;
+		},
+
+		"rates" => {
+			let Some(r) = context.get_rates() else {
+				return FormattedText::new(concat!(
+					"No exchange rates are loaded.\n",
+					"Run [c]daisy --help[n] to see where daisy looks for them.\n\n"
+				).to_string());
+			};
+
+			let mut t = FormattedText::new(format!(
+				concat!(
+					"\n╞═══ [t]Exchange Rates[n] ═══╡\n",
+					"  From:    [c]{}[n]\n",
+					"  Updated: [c]{}[n]\n",
+					"\n",
+				),
+				r.source,
+				r.updated.as_deref().unwrap_or("unknown")
+			));
+
+			for (u, v) in &r.rates {
+				t.push(&format!(
+					"  {}  [c]{} USD[n]\n",
+					FreeUnit::from_whole(*u).to_string(),
+					v.to_string()
+				));
+			}
+
+			t.push("\n\n");
+			return t;
+		},
+
+		"dim" => {
+			return dim::dim(context, &s[first.len()..]);
+		},
+
 		_ => unreachable!("Bad command!")
 	};
 }
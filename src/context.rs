@@ -1,6 +1,13 @@
 use crate::parser::{Expression, Function, Constant};
 use crate::quantity::freeunit_from_string;
+use crate::quantity::Quantity;
+use crate::quantity::Unit;
+use crate::quantity::Rates;
+use crate::errors::DaisyError;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::fmt;
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -12,8 +19,9 @@ pub struct Config {
 	// 2: Full 256 color and special styles
 	pub term_color_type: u8,
 
-	// Should we accept input and print in unicode?
-	//pub enable_unicode: bool,
+	// Should we print in unicode?
+	// If this is off, output is plain ASCII.
+	pub enable_unicode: bool,
 
 	// Should we replace certain strings (like "pi")
 	// with prettier unicode alternatives?
@@ -32,24 +40,55 @@ pub struct Config {
 	//
 	// Automatically disabled if enable_super_powers is off.
 	pub enable_one_over_power: bool,
+
+	// Should we write results in the best unit?
+	// (`kg·m/s²` as `N`, `0.0032 m` as `3.2 mm`)
+	//
+	// Results of `to` are never changed.
+	pub enable_simplify: bool,
+
+	// How deep user function calls may nest.
+	// Each call uses some native stack,
+	// so this should be lower on targets with small stacks.
+	pub max_call_depth: usize,
+
+	// Should errors suggest names the user may have meant?
+	// The prompt preview turns this off, since it runs on every keystroke.
+	pub enable_suggestions: bool,
+
+	// Should native functions be called?
+	// If this is off, calls to them are left unevaluated.
+	// The prompt preview turns this off, since the program
+	// we're embedded in may not expect calls for unfinished input.
+	pub enable_native_functions: bool,
 }
 
+// The default for `Config::max_call_depth`.
+// Deeper calls are almost certainly runaway recursion.
+// Each call takes a few kilobytes of stack in debug builds,
+// so this must fit in a 2 MiB thread (and in wasm's 1 MiB stack).
+pub const DEFAULT_CALL_DEPTH: usize = 256;
+
 impl Config {
 	pub fn new() -> Config {
 		Config{
 			term_color_type: 2,
 			enable_substituion: true,
-			//enable_unicode: true,
+			enable_unicode: true,
 			enable_super_powers: true,
-			enable_one_over_power: true
+			enable_one_over_power: true,
+			enable_simplify: true,
+			max_call_depth: DEFAULT_CALL_DEPTH,
+			enable_suggestions: true,
+			enable_native_functions: true
 		}
 	}
 
 	pub fn check(&mut self) {
-		//if !self.enable_unicode {
-		//	self.enable_substituion = false;
-		//	self.enable_super_powers = false;
-		//}
+		if !self.enable_unicode {
+			self.enable_substituion = false;
+			self.enable_super_powers = false;
+		}
 
 		if !self.enable_super_powers {
 			self.enable_one_over_power = false
@@ -60,17 +99,112 @@ impl Config {
 
 
 
+
+// A function provided by the program daisy is embedded in.
+// These are called with fully-evaluated arguments.
+// They are never called while previewing input that hasn't
+// been entered yet, see `Config::enable_native_functions`.
+#[derive(Clone)]
+pub struct NativeFunction {
+	arity: usize,
+
+	// The unit each argument is converted to before calling `function`.
+	// Arguments with incompatible units are rejected.
+	// `None` accepts any unit and passes the argument through unchanged.
+	units: Vec<Option<Unit>>,
+
+	function: Rc<dyn Fn(&[Quantity]) -> Result<Quantity, DaisyError>>
+}
+
+impl fmt::Debug for NativeFunction {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("NativeFunction")
+			.field("arity", &self.arity)
+			.field("units", &self.units)
+			.finish_non_exhaustive()
+	}
+}
+
+impl NativeFunction {
+	// A function that takes `arity` arguments with any units.
+	pub fn new<F>(arity: usize, f: F) -> NativeFunction
+	where F: Fn(&[Quantity]) -> Result<Quantity, DaisyError> + 'static {
+		return NativeFunction {
+			arity,
+			units: vec![None; arity],
+			function: Rc::new(f)
+		};
+	}
+
+	// A function with one argument for each entry in `units`.
+	// Use an empty unit to require a unitless argument.
+	pub fn new_with_units<F>(units: Vec<Option<Unit>>, f: F) -> NativeFunction
+	where F: Fn(&[Quantity]) -> Result<Quantity, DaisyError> + 'static {
+		return NativeFunction {
+			arity: units.len(),
+			units,
+			function: Rc::new(f)
+		};
+	}
+
+	pub fn arity(&self) -> usize { self.arity }
+	pub fn units(&self) -> &[Option<Unit>] { &self.units }
+
+	// Check and convert arguments, then call this function.
+	// `name` is the name this function was registered with.
+	// On failure, returns the index of the bad argument.
+	pub fn call(&self, context: &Context, name: &str, args: Vec<Quantity>) -> Result<Quantity, (Option<usize>, DaisyError)> {
+		if args.len() != self.arity {
+			return Err((None, DaisyError::BadArguments(name.to_string(), self.arity, args.len())));
+		}
+
+		let mut converted = Vec::with_capacity(args.len());
+		for (i, (q, u)) in args.into_iter().zip(self.units.iter()).enumerate() {
+			let Some(u) = u else { converted.push(q); continue; };
+
+			let mut target = Quantity::new_rational(1f64).unwrap();
+			target.set_unit(u.clone());
+
+			let Some(c) = q.convert_to(target, context.get_rates()) else {
+				let a = q.unit.to_base(context.get_rates()).unit;
+				let b = u.to_base(context.get_rates()).unit;
+				return Err((Some(i), DaisyError::IncompatibleUnits(
+					if a.unitless() { String::from("scalar") } else { a.display(context) },
+					if b.unitless() { String::from("scalar") } else { b.display(context) },
+					None
+				)));
+			};
+			converted.push(c);
+		}
+
+		return (self.function)(&converted).map_err(|e| (None, e));
+	}
+}
+
+
+
 #[derive(Debug)]
-//#[derive(Clone)]
+#[derive(Clone)]
 pub struct Context {
 	pub config: Config,
 
-	history: Vec<Expression>,
+	// Input strings and their results.
+	// Results are numbered from 1.
+	// This is shared between clones, so copies of a context
+	// (like the one the prompt preview uses) are cheap.
+	history: Rc<Vec<(String, Expression)>>,
 	variables: HashMap<String, Expression>,
 	functions: HashMap<String, (Vec<String>, Expression)>,
+	native_functions: HashMap<String, NativeFunction>,
+
+	// Exchange rates for currency conversion.
+	// Without these, currencies can't be converted.
+	rates: Option<Rc<Rates>>,
 
 	// Shadow variables, for function evaluation.
-	shadow: HashMap<String, Option<Expression>>
+	// Each function call gets its own frame,
+	// and only the top frame is visible.
+	frames: Vec<HashMap<String, Option<Expression>>>
 }
 
 // General functions
@@ -78,14 +212,46 @@ impl Context {
 	pub fn new() -> Context {
 		Context {
 			config: Config::new(),
-			history: Vec::new(),
+			history: Rc::new(Vec::new()),
 			variables: HashMap::new(),
 			functions: HashMap::new(),
-			shadow: HashMap::new()
+			native_functions: HashMap::new(),
+			rates: None,
+			frames: Vec::new()
 		}
 	}
 
-	pub fn push_hist(&mut self, t: Expression) { self.history.push(t); }
+	// Save a result, returns its number.
+	pub fn push_hist(&mut self, s: String, t: Expression) -> usize {
+		Rc::make_mut(&mut self.history).push((s, t));
+		return self.history.len();
+	}
+
+	pub fn get_history(&self) -> &Vec<(String, Expression)> {
+		return &self.history;
+	}
+
+	// Use these exchange rates for all conversions.
+	// `None` removes all rates, which makes currencies incompatible.
+	pub fn set_rates(&mut self, r: Option<Rates>) {
+		self.rates = r.map(Rc::new);
+	}
+
+	pub fn get_rates(&self) -> Option<&Rates> {
+		return self.rates.as_deref();
+	}
+
+	// Which result does `s` refer to?
+	// `ans3`, `$3` and `out[3]` all refer to the third result.
+	// Results are numbered from one, so `$0` never exists.
+	pub fn history_index(s: &str) -> Option<usize> {
+		let n = s.strip_prefix("ans")
+			.or_else(|| s.strip_prefix("$"))
+			.or_else(|| s.strip_prefix("out[").and_then(|x| x.strip_suffix("]")))?;
+
+		if n.len() == 0 || !n.chars().all(|c| c.is_ascii_digit()) { return None; }
+		return n.parse().ok();
+	}
 
 
 	pub fn delete(&mut self, s: &String) -> Result<(), ()> {
@@ -108,22 +274,23 @@ impl Context {
 		} else { return Err(()); }
 	}
 
-	// Returns None if this is a "floating" variable
+	// Returns None if this is a "floating" variable,
+	// or if this variable isn't defined.
 	pub fn get_variable(&self, s: &String) -> Option<Expression> {
-		if self.shadow.contains_key(s) {
-			return self.shadow.get(s).unwrap().clone();
+		if let Some(v) = self.frames.last().and_then(|f| f.get(s)) {
+			return v.clone();
 		}
 
 		let v: Option<&Expression>;
 		if s == "ans" {
-			v = self.history.last();
+			v = self.history.last().map(|x| &x.1);
+		} else if let Some(i) = Context::history_index(s) {
+			v = i.checked_sub(1).and_then(|i| self.history.get(i)).map(|x| &x.1);
 		} else {
 			v = self.variables.get(s);
 		}
 
-		if v.is_some() {
-			return Some(v.unwrap().clone());
-		} else { panic!() }
+		return v.cloned();
 	}
 
 	// Can we define a new variable with this name?
@@ -131,7 +298,8 @@ impl Context {
 		if {
 			Function::from_string(s).is_some() ||
 			Constant::from_string(s).is_some() ||
-			freeunit_from_string(s).is_some()
+			freeunit_from_string(s).is_some() ||
+			self.native_functions.contains_key(s)
 		} { return false }
 
 		for c in s.to_lowercase().chars() {
@@ -141,7 +309,10 @@ impl Context {
 		}
 
 		return match s {
-			"ans" => false,
+			"ans"
+			| "true" | "false"
+			| "and" | "or" | "not"
+			=> false,
 			_ => true
 		}
 	}
@@ -153,9 +324,13 @@ impl Context {
 				s == "ans" &&
 				self.history.len() != 0
 			) ||
+			Context::history_index(s).is_some_and(|i| i != 0 && i <= self.history.len()) ||
 			(
 				self.valid_varible(s) &&
-				(self.variables.contains_key(s) || self.shadow.contains_key(s))
+				(
+					self.variables.contains_key(s) ||
+					self.frames.last().is_some_and(|f| f.contains_key(s))
+				)
 			)
 		};
 	}
@@ -164,13 +339,71 @@ impl Context {
 		return &self.variables
 	}
 
-	pub fn add_shadow(&mut self, s: String, v: Option<Expression>) {
-		if !self.valid_varible(&s) { panic!() }
-		self.shadow.insert(s, v);
+	// Floating variables refer to other variables,
+	// and are re-evaluated every time they are used.
+	pub fn is_floating(&self, s: &str) -> bool {
+		return match self.variables.get(s) {
+			Some(v) => v.get_variables().len() != 0,
+			None => false
+		};
+	}
+
+	// The variables that variable `s` refers to, sorted.
+	pub fn get_dependencies(&self, s: &str) -> Vec<String> {
+		let Some(v) = self.variables.get(s) else { return Vec::new() };
+
+		let mut d: Vec<String> = v.get_variables().into_iter().map(|(_, x)| x).collect();
+		d.sort();
+		d.dedup();
+		return d;
+	}
+
+	// If defining `s` as `e` would make `s` depend on itself,
+	// returns that cycle. The cycle starts and ends with `s`.
+	pub fn find_cycle(&self, s: &str, e: &Expression) -> Option<Vec<String>> {
+		let mut seen: HashSet<String> = HashSet::new();
+
+		for (_, d) in e.get_variables() {
+			if let Some(mut p) = self.cycle_path(s, &d, &mut seen) {
+				p.insert(0, s.to_string());
+				return Some(p);
+			}
+		}
+
+		return None;
+	}
+
+	// Depth-first search for a path from `at` to `target`.
+	fn cycle_path(&self, target: &str, at: &String, seen: &mut HashSet<String>) -> Option<Vec<String>> {
+		if at == target { return Some(vec![at.clone()]); }
+		if !seen.insert(at.clone()) { return None; }
+
+		for d in self.get_dependencies(at) {
+			if let Some(mut p) = self.cycle_path(target, &d, seen) {
+				p.insert(0, at.clone());
+				return Some(p);
+			}
+		}
+
+		return None;
+	}
+
+	// Start a new function call.
+	// Fails if calls are nested too deeply.
+	pub fn push_frame(&mut self) -> Result<(), ()> {
+		if self.frames.len() >= self.config.max_call_depth { return Err(()); }
+		self.frames.push(HashMap::new());
+		return Ok(());
 	}
 
-	pub fn clear_shadow(&mut self) {
-		self.shadow = HashMap::new();
+	pub fn pop_frame(&mut self) {
+		self.frames.pop();
+	}
+
+	// Add a shadow variable to the current frame.
+	pub fn add_shadow(&mut self, s: String, v: Option<Expression>) {
+		if !self.valid_varible(&s) { panic!() }
+		self.frames.last_mut().unwrap().insert(s, v);
 	}
 
 }
@@ -191,7 +424,7 @@ impl Context {
 	}
 
 	pub fn get_function(&self, s: &String) -> Option<(Vec<String>, Expression)> {
-		return Some(self.functions.get(s).unwrap().clone());
+		return self.functions.get(s).cloned();
 	}
 
 	pub fn is_function(&self, s: &str) -> bool {
@@ -200,4 +433,47 @@ impl Context {
 	pub fn get_functions(&self) -> &HashMap<String, (Vec<String>, Expression)> {
 		return &self.functions
 	}
+
+	// Run `f` with function `s` defined, then restore whatever `s` was.
+	// Definitions use this to refer to themselves before they're saved.
+	pub fn with_function<T>(
+		&mut self, s: &String, a: &Vec<String>, t: &Expression,
+		f: impl FnOnce(&mut Context) -> T
+	) -> T {
+		let old_v = self.variables.remove(s);
+		let old_f = self.functions.insert(s.clone(), (a.clone(), t.clone()));
+
+		let r = f(self);
+
+		self.functions.remove(s);
+		if let Some(x) = old_f { self.functions.insert(s.clone(), x); }
+		if let Some(x) = old_v { self.variables.insert(s.clone(), x); }
+		return r;
+	}
+}
+
+
+// Native function manipulation
+impl Context {
+	// Register a function implemented in Rust.
+	// Native functions can't be redefined or deleted by the user.
+	pub fn push_native_function(&mut self, s: String, f: NativeFunction) -> Result<(), ()> {
+		if !self.valid_function(&s) && !self.is_native_function(&s) { return Err(()); }
+		self.variables.remove(&s);
+		self.functions.remove(&s);
+		self.native_functions.insert(s, f);
+		return Ok(());
+	}
+
+	pub fn get_native_function(&self, s: &str) -> Option<&NativeFunction> {
+		return self.native_functions.get(s);
+	}
+
+	pub fn is_native_function(&self, s: &str) -> bool {
+		return self.native_functions.contains_key(s);
+	}
+
+	pub fn get_native_functions(&self) -> &HashMap<String, NativeFunction> {
+		return &self.native_functions
+	}
 }
\ No newline at end of file
@@ -15,13 +15,30 @@ pub enum DaisyError {
 
 	// Evaluation errors
 	BadMath,
+	// An uncertainty became infinite, like `sqrt(0 ± 0.1)`
+	InfiniteUncertainty,
+	NegativeUncertainty,
 	TooBig,
 	ZeroDivision,
 	IncompatibleUnit,
-	IncompatibleUnits(String, String),
-	Undefined(String),
+	// The last string says how to make these compatible
+	IncompatibleUnits(String, String, Option<String>),
+	// A value and the list of units we couldn't split it into
+	IncompatibleUnitList(String, String),
+	BadUnitList,
+	// Names the user may have meant
+	Undefined(String, Vec<String>),
 	EvaluationError,
-	BadArguments(String, usize, usize)
+	BadArguments(String, usize, usize),
+	ExpectedBoolean,
+	UnexpectedBoolean,
+	TooDeep,
+	CircularDefinition(Vec<String>),
+	// The currency we have no exchange rate for.
+	// None if no rates are loaded at all.
+	NoRates(Option<String>),
+	// The history entry we wanted (0 for `ans`) and how many there are
+	NoHistory(usize, usize)
 }
 
 impl DaisyError {
@@ -72,6 +89,16 @@ impl DaisyError {
 					"[e]Evaluation Error:[n] Failed to evaluate expression".to_string()
 				);
 			},
+			DaisyError::InfiniteUncertainty => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] Uncertainty is infinite, this function is too steep here".to_string()
+				);
+			},
+			DaisyError::NegativeUncertainty => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] Uncertainties can't be negative".to_string()
+				);
+			},
 			DaisyError::TooBig => {
 				return FormattedText::new(
 					"[e]Evaluation Error:[n] Number too big".to_string()
@@ -87,16 +114,40 @@ impl DaisyError {
 					"[e]Evaluation Error:[n] Incompatible unit".to_string()
 				);
 			},
-			DaisyError::IncompatibleUnits(a, b) => {
+			DaisyError::IncompatibleUnits(a, b, h) => {
+				return FormattedText::new(format!(
+					"[e]Evaluation Error:[n] Incompatible units ([c]{a}[n] and [c]{b}[n]){}",
+					h.as_ref().map(|h| format!(", {h}")).unwrap_or_default()
+				));
+			},
+			DaisyError::IncompatibleUnitList(a, b) => {
 				return FormattedText::new(format!(
-					"[e]Evaluation Error:[n] Incompatible units ([c]{a}[n] and [c]{b}[n])"
+					"[e]Evaluation Error:[n] Can't convert [c]{a}[n] to [c]{b}[n], each unit needs the same dimensions as [c]{a}[n]"
 				));
 			},
-			DaisyError::Undefined(s) => {
+			DaisyError::BadUnitList => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] Only units may follow [c]to[n] in a list, like [c]ft, in[n]".to_string()
+				);
+			},
+			DaisyError::NoRates(None) => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] No exchange rates are loaded, see [c]rates[n]".to_string()
+				);
+			},
+			DaisyError::NoRates(Some(s)) => {
 				return FormattedText::new(format!(
-					"[e]Evaluation Error:[n] [c]{s}[n] is not defined"
+					"[e]Evaluation Error:[n] There is no exchange rate for [c]{s}[n], see [c]rates[n]"
 				));
 			},
+			DaisyError::Undefined(s, v) => {
+				let mut t = format!("[e]Evaluation Error:[n] [c]{s}[n] is not defined");
+				if v.len() != 0 {
+					let v: Vec<String> = v.iter().map(|x| format!("[c]{x}[n]")).collect();
+					t.push_str(&format!(". Did you mean {}?", v.join(" or ")));
+				}
+				return FormattedText::new(t);
+			},
 			DaisyError::EvaluationError => {
 				return FormattedText::new(
 					"[e]Evaluation Error:[n] Could not evaluate".to_string()
@@ -107,6 +158,37 @@ impl DaisyError {
 					"[e]Evaluation Error:[n] [c]{s}[n] takes {want} argument{}, but it got {got}",
 					if *want == 1 {""} else {"s"},
 				));
+			},
+			DaisyError::ExpectedBoolean => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] Expected [c]true[n] or [c]false[n]".to_string()
+				);
+			},
+			DaisyError::UnexpectedBoolean => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] Can't do math with [c]true[n] or [c]false[n]".to_string()
+				);
+			},
+			DaisyError::TooDeep => {
+				return FormattedText::new(
+					"[e]Evaluation Error:[n] Too many nested function calls".to_string()
+				);
+			},
+			DaisyError::CircularDefinition(v) => {
+				return FormattedText::new(format!(
+					"[e]Evaluation Error:[n] [c]{}[n] would depend on itself ([c]{}[n])",
+					v[0], v.join(" -> ")
+				));
+			},
+			DaisyError::NoHistory(n, len) => {
+				if *len == 0 {
+					return FormattedText::new(
+						"[e]Evaluation Error:[n] There are no results yet".to_string()
+					);
+				}
+				return FormattedText::new(format!(
+					"[e]Evaluation Error:[n] Result [c]${n}[n] doesn't exist, the last one is [c]${len}[n]"
+				));
 			}
 		}
 	}
@@ -1,11 +1,15 @@
 use crate::parser::Expression;
 use crate::parser::Operator;
+use crate::parser::Function;
 use crate::context::Context;
 use crate::parser::LineLocation;
 use crate::errors::DaisyError;
 
 use super::operator::eval_operator;
 use super::function::eval_function;
+use super::function::eval_if;
+use super::function::eval_user_function;
+use super::suggest::suggest_names;
 
 
 pub fn evaluate(
@@ -39,7 +43,12 @@ pub fn evaluate(
 
 		// "Move up" step.
 		// We move up if we're at a leaf or if we're out of children to move down to.
+		//
+		// `if` is also treated as a leaf, since only one of
+		// its branches should be evaluated.
+		let is_if = matches!(g, Expression::Operator(_, Operator::Function(Function::If), _));
 		if {
+			is_if ||
 			g.is_quantity() ||
 			g.get_args().is_none() ||
 			(coords.len() != 0 && (*coords.last().unwrap() >= g.get_args().unwrap().len()))
@@ -47,25 +56,7 @@ pub fn evaluate(
 
 			// If true, move to the next node
 			let mut move_up = true;
-
-			let new = match g {
-				Expression::Quantity(_, _) => None,
-				Expression::Tuple(_, _) => None,
-				Expression::Constant(_, c) => { Some(evaluate(context, &c.value()).unwrap()) },
-				Expression::Variable(l, s) => {
-					// Don't move up, re-evaluate
-					// This makes variables containing floating variables work properly
-					// (For example, try x = a + 2, a = 2, x. x should evaluate to 4.)
-					move_up = false;
-					if !context.is_varible(&s) {
-						return Err((*l, DaisyError::Undefined(s.clone())));
-					}
-
-					context.get_variable(&s)
-				},
-				Expression::Operator(_, Operator::Function(_), _) => { eval_function(g)? },
-				Expression::Operator(_, _, _) => { eval_operator(context, g)? },
-			};
+			let new = eval_node(context, g, &mut move_up)?;
 
 			if let Some(mut new) = new {
 				if let Expression::Constant(_,_) = g {
@@ -74,6 +65,15 @@ pub fn evaluate(
 					// but other operations might.
 					new.set_linelocation(&g.get_linelocation());
 				}
+
+				// Uncertainties are propagated with derivatives,
+				// which may be infinite (or undefined) at some points.
+				if let Expression::Quantity(l, q) = &new {
+					if !q.uncertainty_is_finite() {
+						return Err((*l, DaisyError::InfiniteUncertainty));
+					}
+				}
+
 				*g = new;
 			} else {
 
@@ -104,5 +104,157 @@ pub fn evaluate(
 		}
 	}
 
+	// Write results in the best unit,
+	// unless the user asked for a specific one.
+	//
+	// Variables and results were written this way when they were saved,
+	// so recalling one like `ans` or `$1` keeps the unit it was given.
+	match t {
+		Expression::Operator(_, Operator::UnitConvert, _)
+		| Expression::Variable(_, _)
+		=> {},
+		_ => simplify(context, &mut root)
+	}
+
 	return Ok(root);
-}
\ No newline at end of file
+}
+
+
+// Evaluate one node of an expression tree.
+// Returns None if this node can't be evaluated yet.
+//
+// `evaluate` and user functions call each other once per nested call,
+// so this is kept apart from `evaluate` and each branch is a direct call.
+// This keeps deep recursion's stack small, even in debug builds.
+fn eval_node(
+	context: &mut Context,
+	g: &Expression,
+	move_up: &mut bool
+) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
+	return match g {
+		Expression::Quantity(_, _) => Ok(None),
+		Expression::Boolean(_, _) => Ok(None),
+		Expression::Tuple(_, _) => Ok(None),
+		Expression::Constant(_, _) => eval_constant(context, g),
+		Expression::Variable(_, _) => {
+			// Don't move up, re-evaluate
+			// This makes variables containing floating variables work properly
+			// (For example, try x = a + 2, a = 2, x. x should evaluate to 4.)
+			*move_up = false;
+			eval_variable(context, g)
+		},
+		Expression::Operator(_, Operator::Function(Function::If), _) => {
+			// Don't move up, evaluate the branch we picked
+			*move_up = false;
+			eval_if(context, g)
+		},
+		Expression::Operator(_, Operator::Function(_), _) => eval_function(context, g),
+		Expression::Operator(_, Operator::UserFunction(_), _) => eval_user_function(context, g),
+		Expression::Operator(_, _, _) => eval_operator(context, g),
+	};
+}
+
+
+fn eval_constant(context: &mut Context, g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
+	let Expression::Constant(_, c) = g else { unreachable!() };
+	return Ok(Some(evaluate(context, &c.value()).unwrap()));
+}
+
+
+fn eval_variable(context: &mut Context, g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
+	let Expression::Variable(l, s) = g else { unreachable!() };
+
+	if !context.is_varible(s) {
+		let n = if s == "ans" { Some(0) } else { Context::history_index(s) };
+		if let Some(n) = n {
+			return Err((*l, DaisyError::NoHistory(n, context.get_history().len())));
+		}
+		return Err((*l, DaisyError::Undefined(s.clone(), suggest_names(context, s))));
+	}
+
+	return match context.get_variable(s) {
+		// Floating variables are evaluated in an empty frame.
+		// This keeps function arguments from leaking into them,
+		// and makes errors point at this variable.
+		Some(v) if v.get_variables().len() != 0 => {
+			if context.push_frame().is_err() {
+				return Err((*l, DaisyError::TooDeep));
+			}
+			let r = evaluate(context, &v);
+			context.pop_frame();
+
+			let mut r = r.map_err(|(_, e)| (*l, e))?;
+			r.set_linelocation(l);
+			Ok(Some(r))
+		},
+
+		v => Ok(v)
+	};
+}
+
+
+// Write a quantity in the best unit.
+fn simplify(context: &Context, g: &mut Expression) {
+	if let Expression::Quantity(l, q) = g {
+		*g = Expression::Quantity(*l, q.simplify(context));
+	}
+}
+
+
+// Make sure the body of a function definition evaluates.
+// `context` is left as it was.
+pub fn check_function(
+	context: &mut Context,
+	name: &String,
+	args: &Vec<String>,
+	body: &Expression
+) -> Result<(), (LineLocation, DaisyError)> {
+
+	// Functions may call themselves,
+	// so check them while they're defined.
+	return context.with_function(name, args, body, |c| {
+		// Arguments are floating while we check
+		if c.push_frame().is_err() {
+			return Err((body.get_linelocation(), DaisyError::TooDeep));
+		}
+		for a in args { c.add_shadow(a.clone(), None); }
+
+		let r = evaluate(c, body);
+		c.pop_frame();
+		return r.map(|_| ());
+	});
+}
+
+
+
+// Evaluate the value of variable `name`.
+// Undefined and floating variables are left as they are,
+// so the result may be floating.
+pub fn evaluate_variable(
+	context: &mut Context,
+	name: &String,
+	g: &Expression
+) -> Result<Expression, (LineLocation, DaisyError)> {
+
+	context.push_frame().unwrap();
+	for (_, s) in g.get_variables() {
+		if {
+			context.valid_varible(&s) &&
+			(!context.is_varible(&s) || context.is_floating(&s))
+		} {
+			context.add_shadow(s, None);
+		}
+	}
+
+	let r = evaluate(context, g);
+	context.pop_frame();
+	let r = r?;
+
+	if let Some(c) = context.find_cycle(name, &r) {
+		// Point at the reference that starts this cycle
+		let (l, _) = r.get_variables().into_iter().find(|(_, s)| s == &c[1]).unwrap();
+		return Err((l, DaisyError::CircularDefinition(c)));
+	}
+
+	return Ok(r);
+}
@@ -1,4 +1,5 @@
 use crate::parser::Expression;
+use crate::parser::Constant;
 use crate::parser::Function;
 use crate::parser::Operator;
 use crate::parser::LineLocation;
@@ -7,6 +8,9 @@ use crate::quantity::WholeUnit;
 use crate::quantity::Quantity;
 use crate::quantity::Scalar;
 use crate::errors::DaisyError;
+use crate::context::Context;
+use super::evaluate;
+use super::suggest::suggest_names;
 
 
 // If unitless, do nothing
@@ -19,14 +23,63 @@ fn to_radians(q: Quantity) -> Result<Quantity, ()> {
 
 	let mut r = Quantity::new_rational(1f64).unwrap();
 	r.insert_unit(FreeUnit::from_whole(WholeUnit::Radian), Scalar::new_rational(1f64).unwrap());
-	let Some(q) = q.convert_to(r) else { return Err(()) };
+	let Some(q) = q.convert_to(r, None) else { return Err(()) };
 
 	return Ok(q.without_unit());
 }
 
 
 
-pub fn eval_function(g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
+// If `q` radians is an exact multiple of π/12 (15°),
+// return that multiple, between 0 and 23.
+//
+// Used to give exact values for trig functions.
+// This works because both `q` and `pi` are rational.
+fn pi_twelfths(q: &Quantity) -> Option<i64> {
+	if q.uncertainty.is_some() { return None; }
+	let Expression::Quantity(_, pi) = Constant::Pi.value() else { unreachable!() };
+
+	let n = q.clone() * Quantity::new_rational(12f64).unwrap() / pi;
+	if !n.scalar.is_rational() || !n.scalar.is_int() { return None; }
+
+	let n = n.scalar.to_f64();
+	if n.abs() > 1e15 { return None; }
+	return Some((n as i64).rem_euclid(24));
+}
+
+// Exact sine of `k` twelfths of π, if it is rational
+fn exact_sin(k: i64) -> Option<Quantity> {
+	let (t, b) = match k {
+		0 | 12 => (0, 1),
+		6 => (1, 1),
+		18 => (-1, 1),
+		2 | 10 => (1, 2),
+		14 | 22 => (-1, 2),
+		_ => return None
+	};
+	return Some(Quantity::from_scalar(Scalar::new_rational_from_frac(t, b).unwrap()));
+}
+
+// Exact tangent of `k` twelfths of π, if it is rational
+fn exact_tan(k: i64) -> Option<Quantity> {
+	let t = match k % 12 {
+		0 => 0,
+		3 => 1,
+		9 => -1,
+		_ => return None
+	};
+	return Some(Quantity::from_scalar(Scalar::new_rational_from_frac(t, 1).unwrap()));
+}
+
+// 1/x, if x isn't zero.
+fn recip(x: Quantity) -> Option<Quantity> {
+	if x.is_zero() { return None; }
+	return Some(Quantity::new_rational(1f64).unwrap() / x);
+}
+
+
+
+pub fn eval_function(context: &Context, g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
 
 	let Expression::Operator(loc, Operator::Function(f), args) = g else {unreachable!()};
 
@@ -41,11 +94,17 @@ pub fn eval_function(g: &Expression) -> Result<Option<Expression>, (LineLocation
 		))
 	};
 
+	if let Expression::Boolean(l, _) = a {
+		return Err((*l + *loc, DaisyError::UnexpectedBoolean));
+	}
+
 	let Expression::Quantity(l, q) = a else { return Ok(None); };
 
 	match f {
+		Function::If => unreachable!("If is handled seperately."),
+
 		Function::NoUnit => { return Ok(Some(Expression::Quantity(*loc + *l, q.without_unit()))); }
-		Function::ToBase => { return Ok(Some(Expression::Quantity(*loc + *l, q.convert_to_base()))); }
+		Function::ToBase => { return Ok(Some(Expression::Quantity(*loc + *l, q.convert_to_base(context.get_rates())))); }
 
 
 
@@ -78,26 +137,32 @@ pub fn eval_function(g: &Expression) -> Result<Option<Expression>, (LineLocation
 
 		Function::Sin => {
 			let Ok(q) = to_radians(q.clone()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)); };
+			if let Some(r) = pi_twelfths(&q).and_then(exact_sin) { return Ok(Some(Expression::Quantity(*loc + *l, r))); }
 			return Ok(Some(Expression::Quantity(*loc + *l, q.sin())));
 		},
 		Function::Cos => {
 			let Ok(q) = to_radians(q.clone()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)); };
+			if let Some(r) = pi_twelfths(&q).and_then(|k| exact_sin((k + 6) % 24)) { return Ok(Some(Expression::Quantity(*loc + *l, r))); }
 			return Ok(Some(Expression::Quantity(*loc + *l, q.cos())));
 		},
 		Function::Tan => {
 			let Ok(q) = to_radians(q.clone()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)); };
+			if let Some(r) = pi_twelfths(&q).and_then(exact_tan) { return Ok(Some(Expression::Quantity(*loc + *l, r))); }
 			return Ok(Some(Expression::Quantity(*loc + *l, q.tan())));
 		},
 		Function::Csc => {
 			let Ok(q) = to_radians(q.clone()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)); };
+			if let Some(r) = pi_twelfths(&q).and_then(exact_sin).and_then(recip) { return Ok(Some(Expression::Quantity(*loc + *l, r))); }
 			return Ok(Some(Expression::Quantity(*loc + *l, q.csc())));
 		},
 		Function::Sec => {
 			let Ok(q) = to_radians(q.clone()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)); };
+			if let Some(r) = pi_twelfths(&q).and_then(|k| exact_sin((k + 6) % 24)).and_then(recip) { return Ok(Some(Expression::Quantity(*loc + *l, r))); }
 			return Ok(Some(Expression::Quantity(*loc + *l, q.sec())));
 		},
 		Function::Cot => {
 			let Ok(q) = to_radians(q.clone()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)); };
+			if let Some(r) = pi_twelfths(&q).and_then(|k| exact_tan((30 - k) % 24)) { return Ok(Some(Expression::Quantity(*loc + *l, r))); }
 			return Ok(Some(Expression::Quantity(*loc + *l, q.cot())));
 		},
 		Function::Sinh => {
@@ -154,7 +219,7 @@ pub fn eval_function(g: &Expression) -> Result<Option<Expression>, (LineLocation
 		Function::ToCelsius => {
 			let mut k = Quantity::new_rational(1f64).unwrap();
 			k.insert_unit(FreeUnit::from_whole(WholeUnit::Kelvin), Scalar::new_rational(1f64).unwrap());
-			let Some(q) = q.convert_to(k) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)) };
+			let Some(q) = q.convert_to(k, context.get_rates()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)) };
 
 			let mut r = q.without_unit();
 			r += Quantity::new_rational(-273.15f64).unwrap();
@@ -164,7 +229,7 @@ pub fn eval_function(g: &Expression) -> Result<Option<Expression>, (LineLocation
 		Function::ToFahrenheit => {
 			let mut k = Quantity::new_rational(1f64).unwrap();
 			k.insert_unit(FreeUnit::from_whole(WholeUnit::Kelvin), Scalar::new_rational(1f64).unwrap());
-			let Some(q) = q.convert_to(k) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)) };
+			let Some(q) = q.convert_to(k, context.get_rates()) else { return Err((*loc + *l, DaisyError::IncompatibleUnit)) };
 
 			let mut r = q.without_unit();
 			r *= Quantity::new_rational_from_frac(9i64, 5i64).unwrap();
@@ -193,4 +258,110 @@ pub fn eval_function(g: &Expression) -> Result<Option<Expression>, (LineLocation
 			return Ok(Some(Expression::Quantity(*loc + *l, r)));
 		}
 	}
-}
\ No newline at end of file
+}
+
+
+// `if` only evaluates the branch it picks.
+// This returns that branch, unevaluated.
+pub fn eval_if(context: &mut Context, g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
+
+	let Expression::Operator(loc, Operator::Function(Function::If), args) = g else {unreachable!()};
+
+	if args.len() != 1 {panic!()};
+	let a = &args[0];
+
+	let Expression::Tuple(_, v) = a else {
+		return Err((
+			a.get_linelocation() + *loc,
+			DaisyError::BadArguments(String::from("if"), 3, 1)
+		));
+	};
+
+	if v.len() != 3 {
+		return Err((
+			a.get_linelocation() + *loc,
+			DaisyError::BadArguments(String::from("if"), 3, v.len())
+		));
+	}
+
+	return match evaluate(context, &v[0])? {
+		Expression::Boolean(_, true) => Ok(Some(v[1].clone())),
+		Expression::Boolean(_, false) => Ok(Some(v[2].clone())),
+
+		Expression::Quantity(l, _)
+		| Expression::Tuple(l, _)
+		=> Err((l, DaisyError::ExpectedBoolean)),
+
+		// We can't pick a branch yet
+		_ => Ok(None)
+	};
+}
+
+
+// User functions are evaluated in their own frame.
+// This is kept out of eval_operator to keep deep recursion's stack small.
+pub fn eval_user_function(context: &mut Context, g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
+
+	let Some((exp, args_ll)) = enter_user_function(context, g)? else {
+		return Ok(None);
+	};
+
+	let r = evaluate(context, &exp);
+	context.pop_frame();
+
+	return r
+		.map(|mut r| { r.set_linelocation(&args_ll); Some(r) })
+		.map_err(|(_, err)| (args_ll, err));
+}
+
+// Start a call to the function `g` calls, with its arguments in a new frame.
+// Returns the function's body and the location of this call,
+// or None if some arguments haven't been evaluated yet.
+//
+// This is kept apart from eval_user_function,
+// so that it isn't on the stack while we recurse.
+fn enter_user_function(context: &mut Context, g: &Expression) -> Result<Option<(Expression, LineLocation)>, (LineLocation, DaisyError)> {
+
+	let Expression::Operator(op_loc, Operator::UserFunction(s), args) = g else {unreachable!()};
+
+	let Some((sh_vars, exp)) = context.get_function(s) else {
+		return Err((*op_loc, DaisyError::Undefined(s.clone(), suggest_names(context, s))));
+	};
+
+	if args.len() != 1 {panic!()};
+	let a = &args[0];
+
+	let v: Vec<&Expression> = match a {
+		Expression::Tuple(_, v) => v.iter().collect(),
+		_ => vec![a]
+	};
+
+	if sh_vars.len() != v.len() {
+		return Err((
+			a.get_linelocation() + *op_loc,
+			DaisyError::BadArguments(s.clone(), sh_vars.len(), v.len())
+		));
+	}
+
+	// Wait until all arguments have been evaluated.
+	// Arguments are evaluated in the caller's frame.
+	let mut args_ll = op_loc.clone();
+	for x in &v {
+		match x {
+			Expression::Quantity(_, _)
+			| Expression::Boolean(_, _)
+			=> { args_ll += x.get_linelocation(); },
+			_ => { return Ok(None); }
+		}
+	}
+
+	if context.push_frame().is_err() {
+		return Err((args_ll, DaisyError::TooDeep));
+	}
+
+	for (n, x) in sh_vars.into_iter().zip(v.into_iter()) {
+		context.add_shadow(n, Some(x.clone()));
+	}
+
+	return Ok(Some((exp, args_ll)));
+}
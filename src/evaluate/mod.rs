@@ -1,5 +1,8 @@
 mod operator;
 mod function;
 mod evaluate;
+mod suggest;
 
-pub use self::evaluate::evaluate;
\ No newline at end of file
+pub use self::evaluate::evaluate;
+pub use self::evaluate::check_function;
+pub use self::evaluate::evaluate_variable;
\ No newline at end of file
@@ -1,72 +1,314 @@
+use std::collections::VecDeque;
+
 use crate::parser::LineLocation;
 use crate::quantity::Quantity;
+use crate::quantity::Scalar;
+use crate::quantity::Unit;
+use crate::quantity::FreeUnit;
+use crate::quantity::WholeUnit;
 use crate::parser::Operator;
 use crate::parser::Expression;
 use crate::context::Context;
 use crate::errors::DaisyError;
-use super::evaluate;
+
+use super::suggest::conversion_hint;
+
+
+// If both units have a currency, they're probably incompatible
+// because we're missing an exchange rate. This returns an error that says so.
+fn missing_rate(context: &Context, a: &Unit, b: &Unit) -> Option<DaisyError> {
+	let is_currency = |u: &FreeUnit| u.whole() == WholeUnit::Usd || WholeUnit::rate_units().contains(&u.whole());
+	if !a.get_val().keys().any(is_currency) || !b.get_val().keys().any(is_currency) { return None; }
+
+	let Some(r) = context.get_rates() else { return Some(DaisyError::NoRates(None)) };
+	let u = a.get_val().keys().chain(b.get_val().keys())
+		.find(|u| WholeUnit::rate_units().contains(&u.whole()) && r.get(u.whole()).is_none())?;
+	return Some(DaisyError::NoRates(Some(u.to_string())));
+}
+
+
+// The error we get when we can't convert `a` to the unit of `b`.
+fn incompatible_units(context: &Context, a: &Expression, b: &Expression) -> DaisyError {
+	let Expression::Quantity(_, va) = a else { unreachable!() };
+	let Expression::Quantity(_, vb) = b else { unreachable!() };
+	if let Some(e) = missing_rate(context, &va.unit, &vb.unit) { return e; }
+
+	let a_s = if va.convert_to_base(context.get_rates()).unit.unitless() { String::from("scalar") } else { a.display(context) };
+	let b_s = if vb.convert_to_base(context.get_rates()).unit.unitless() { String::from("scalar") } else { b.display(context) };
+	return DaisyError::IncompatibleUnits(a_s, b_s, conversion_hint(context, &va.unit, &vb.unit));
+}
+
+
+// `b`, with units written like those in `a` where they're compatible.
+// Quantity math doesn't know about exchange rates,
+// so currencies must match before we add or multiply them.
+fn match_units(context: &Context, a: &Quantity, b: &Quantity) -> Quantity {
+	let mut b = b.clone();
+	b.match_units(a, context.get_rates());
+	return b;
+}
+
+
+// Split `q` into whole numbers of each unit in `units`,
+// with the remainder in the last unit.
+// For example, `5.3 ft to ft, in` is `(5 ft, 3.6 in)`.
+fn convert_to_parts(
+	context: &Context,
+	a: &Expression,
+	q: &Quantity,
+	units: &VecDeque<Expression>,
+	loc: LineLocation
+) -> Result<Expression, (LineLocation, DaisyError)> {
+	// Every item must be a plain unit, `5 ft to ft, 2 in` makes no sense.
+	for u in units {
+		let Expression::Quantity(lu, vu) = u else {
+			return Err((u.get_linelocation(), DaisyError::BadUnitList));
+		};
+		if !vu.is_one() || vu.unitless() { return Err((*lu, DaisyError::BadUnitList)); }
+	}
+
+	let mut parts: VecDeque<Expression> = VecDeque::with_capacity(units.len());
+	let mut r = q.clone();
+
+	// Whole parts are exact, the remainder keeps all uncertainty.
+	r.uncertainty = None;
+
+	for (i, u) in units.iter().enumerate() {
+		let Expression::Quantity(lu, vu) = u else { unreachable!() };
+
+		let Some(n) = r.convert_to(vu.clone(), context.get_rates()) else {
+			return Err((loc, DaisyError::IncompatibleUnitList(
+				if q.convert_to_base(context.get_rates()).unit.unitless() { String::from("scalar") } else { a.display(context) },
+				Expression::Tuple(loc, units.clone()).display(context)
+			)));
+		};
+
+		// The last unit gets whatever is left
+		if i == units.len() - 1 {
+			let mut n = n;
+			n.uncertainty = q.convert_to(vu.clone(), context.get_rates()).unwrap().uncertainty;
+			parts.push_back(Expression::Quantity(*lu, n));
+			break;
+		}
+
+		// Round toward zero, so every part has the same sign
+		let x = n.without_unit();
+		let mut w = if x.is_negative() { -(-x).floor() } else { x.floor() };
+		w.set_unit(vu.unit.clone());
+
+		r = r - w.clone();
+		parts.push_back(Expression::Quantity(*lu, w));
+	}
+
+	return Ok(Expression::Tuple(loc, parts));
+}
 
 
 pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Expression>, (LineLocation, DaisyError)> {
 
 	let Expression::Operator(op_loc, op, args) = g else {panic!()};
 
+	// Only logic, comparisons and user functions may take booleans
+	match op {
+		Operator::And
+		| Operator::Or
+		| Operator::Not
+		| Operator::Equal
+		| Operator::NotEqual
+		| Operator::UserFunction(_)
+		=> {},
+
+		_ => {
+			let mut v: Vec<&Expression> = args.iter().collect();
+			if let Operator::NativeFunction(_) = op {
+				if let Expression::Tuple(_, t) = &args[0] { v = t.iter().collect(); }
+			}
+
+			for a in v {
+				if let Expression::Boolean(l, _) = a {
+					return Err((*l + *op_loc, DaisyError::UnexpectedBoolean));
+				}
+			}
+		}
+	}
+
+	// A list of units from `to`, like `1 m to ft, in`, is a final result.
+	// Only functions take tuples.
+	if !matches!(op, Operator::Function(_) | Operator::UserFunction(_) | Operator::NativeFunction(_)) {
+		for (i, a) in args.iter().enumerate() {
+			if *op == Operator::UnitConvert && i == 1 { continue; }
+			if let Expression::Tuple(l, _) = a {
+				return Err((*l + *op_loc, DaisyError::BadTuple));
+			}
+		}
+	}
+
 	match op {
 		Operator::Function(_) => unreachable!("Functions are handled seperately."),
 
-		Operator::UserFunction(s) => {
-			let (sh_vars, exp) = context.get_function(s).unwrap();
+		Operator::UserFunction(_) => unreachable!("User functions are handled seperately."),
+
+		Operator::NativeFunction(s) => {
+			let Some(f) = context.get_native_function(s) else { unreachable!() };
 
 			if args.len() != 1 {panic!()};
 			let a = &args[0];
-			let mut args_ll = op_loc.clone();
 
-			if sh_vars.len() == 1 {
-				if let Expression::Tuple(l, v) = a {
-					return Err((
-						*l + *op_loc,
-						DaisyError::BadArguments(s.clone(), 1, v.len())
-					))
-				};
-
-				args_ll += a.get_linelocation();
-				context.add_shadow(sh_vars[0].clone(), Some(a.clone()));
-			} else {
-				let Expression::Tuple(l, v) = a else {
-					return Err((
-						a.get_linelocation() + *op_loc,
-						DaisyError::BadArguments(s.clone(), sh_vars.len(), 1)
-					));
-				};
+			let v: Vec<&Expression> = match a {
+				Expression::Tuple(_, v) => v.iter().collect(),
+				_ => vec![a]
+			};
 
-				if sh_vars.len() != v.len() {
-					return Err((
-						*l + *op_loc,
-						DaisyError::BadArguments(s.clone(), sh_vars.len(), v.len())
-					));
+			if f.arity() != v.len() {
+				return Err((
+					a.get_linelocation() + *op_loc,
+					DaisyError::BadArguments(s.clone(), f.arity(), v.len())
+				));
+			}
+
+			// Wait until all arguments have been evaluated
+			let mut q = Vec::with_capacity(v.len());
+			for x in v {
+				let Expression::Quantity(_, x) = x else { return Ok(None); };
+				q.push(x.clone());
+			}
+
+			if !context.config.enable_native_functions { return Ok(None); }
+
+			let l = a.get_linelocation() + *op_loc;
+			return match f.call(context, s, q) {
+				Ok(r) => Ok(Some(Expression::Quantity(l, r))),
+
+				// Point at the argument that caused this error, if we know it.
+				Err((Some(i), e)) => {
+					let la = match a {
+						Expression::Tuple(_, v) => v[i].get_linelocation(),
+						_ => a.get_linelocation()
+					};
+					Err((la, e))
+				},
+				Err((None, e)) => Err((l, e))
+			};
+		},
+
+		Operator::Not => {
+			if args.len() != 1 { panic!() };
+			let a = &args[0];
+
+			return match a {
+				Expression::Boolean(l, v) => Ok(Some(Expression::Boolean(*l + *op_loc, !v))),
+				Expression::Quantity(l, _) | Expression::Tuple(l, _)
+				=> Err((*l + *op_loc, DaisyError::ExpectedBoolean)),
+				_ => Ok(None)
+			};
+		},
+
+		Operator::And
+		| Operator::Or => {
+			if args.len() != 2 { panic!() };
+			let a = &args[0];
+			let b = &args[1];
+
+			for x in [a, b] {
+				if let Expression::Quantity(l, _) | Expression::Tuple(l, _) = x {
+					return Err((*l + *op_loc, DaisyError::ExpectedBoolean));
 				}
+			}
 
-				let mut i = 0;
-				while i < sh_vars.len() {
-					args_ll += v[i].get_linelocation();
-					context.add_shadow(sh_vars[i].clone(), Some(v[i].clone()));
-					i += 1;
+			if let Expression::Boolean(la, va) = a {
+				if let Expression::Boolean(lb, vb) = b {
+					let r = match op {
+						Operator::And => *va && *vb,
+						Operator::Or => *va || *vb,
+						_ => unreachable!()
+					};
+					return Ok(Some(Expression::Boolean(*la + *lb + *op_loc, r)));
 				}
 			}
 
+			return Ok(None);
+		},
 
-			let r = evaluate(context, &exp);
-			context.clear_shadow();
+		Operator::Equal
+		| Operator::NotEqual
+		| Operator::Less
+		| Operator::Greater
+		| Operator::LessEqual
+		| Operator::GreaterEqual => {
+			if args.len() != 2 { panic!() };
+			let a = &args[0];
+			let b = &args[1];
 
-			match r {
-				Ok(mut r) => {
-					r.set_linelocation(&args_ll);
-					return Ok(Some(r));
+			match (a, b) {
+				(Expression::Boolean(la, va), Expression::Boolean(lb, vb)) => {
+					let r = match op {
+						Operator::Equal => va == vb,
+						Operator::NotEqual => va != vb,
+						_ => { return Err((*la + *lb + *op_loc, DaisyError::UnexpectedBoolean)); }
+					};
+					return Ok(Some(Expression::Boolean(*la + *lb + *op_loc, r)));
 				},
 
-				Err( (_, err) ) => {
-					return Err((args_ll, err));
-				}
+				(Expression::Boolean(la, _), Expression::Quantity(lb, _))
+				| (Expression::Quantity(la, _), Expression::Boolean(lb, _))
+				=> {
+					return Err((*la + *lb + *op_loc, DaisyError::UnexpectedBoolean));
+				},
+
+				(Expression::Quantity(la, va), Expression::Quantity(lb, vb)) => {
+					if !va.unit.compatible_with(&vb.unit, context.get_rates()) {
+						if let Some(e) = missing_rate(context, &va.unit, &vb.unit) {
+							return Err((*la + *lb + *op_loc, e));
+						}
+						let va = va.convert_to_base(context.get_rates()).unit;
+						let vb = vb.convert_to_base(context.get_rates()).unit;
+
+						let a_s: String;
+						let b_s: String;
+						if va.unitless() {
+							a_s = String::from("scalar");
+						} else {
+							a_s = va.display(context);
+						}
+
+						if vb.unitless() {
+							b_s = String::from("scalar");
+						} else {
+							b_s = vb.display(context);
+						}
+
+						return Err((
+							*la + *lb + *op_loc,
+							DaisyError::IncompatibleUnits(a_s, b_s, None)
+						));
+					}
+
+					// Compare in a's units.
+					// Exact values are compared exactly. Floats aren't,
+					// so tiny relative differences count as equal.
+					let d = va.clone() - match_units(context, va, vb);
+					let eq = if d.scalar.is_rational() {
+						d.is_zero()
+					} else {
+						let df = d.to_f64().abs();
+						let af = va.to_f64().abs();
+						d.is_zero() || df <= 1e-12 * af.max((af - df).abs())
+					};
+
+					let r = match op {
+						Operator::Equal => eq,
+						Operator::NotEqual => !eq,
+						Operator::Less => !eq && d.is_negative(),
+						Operator::Greater => !eq && !d.is_negative(),
+						Operator::LessEqual => eq || d.is_negative(),
+						Operator::GreaterEqual => eq || !d.is_negative(),
+						_ => unreachable!()
+					};
+
+					return Ok(Some(Expression::Boolean(*la + *lb + *op_loc, r)));
+				},
+
+				_ => { return Ok(None); }
 			}
 		},
 
@@ -86,9 +328,12 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 
 			if let Expression::Quantity(la, a) = a {
 				if let Expression::Quantity(lb, b) = b {
-					if !a.unit.compatible_with(&b.unit) {
-						let a = a.convert_to_base().unit;
-						let b = b.convert_to_base().unit;
+					if !a.unit.compatible_with(&b.unit, context.get_rates()) {
+						if let Some(e) = missing_rate(context, &a.unit, &b.unit) {
+							return Err((*la + *lb + *op_loc, e));
+						}
+						let a = a.convert_to_base(context.get_rates()).unit;
+						let b = b.convert_to_base(context.get_rates()).unit;
 
 						let a_s: String;
 						let b_s: String;
@@ -106,10 +351,10 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 
 						return Err((
 							*la + *lb + *op_loc,
-							DaisyError::IncompatibleUnits(a_s, b_s)
+							DaisyError::IncompatibleUnits(a_s, b_s, None)
 						));
 					}
-					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, a.clone() + b.clone())));
+					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, a.clone() + match_units(context, a, b))));
 				}
 			}
 
@@ -123,7 +368,10 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 
 			if let Expression::Quantity(la, a) = a {
 				if let Expression::Quantity(lb, b) = b {
-					if !a.unit.compatible_with(&b.unit) {
+					if !a.unit.compatible_with(&b.unit, context.get_rates()) {
+						if let Some(e) = missing_rate(context, &a.unit, &b.unit) {
+							return Err((*la + *lb + *op_loc, e));
+						}
 
 						let a_s: String;
 						let b_s: String;
@@ -141,16 +389,44 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 
 						return Err((
 							*la + *lb + *op_loc,
-							DaisyError::IncompatibleUnits(a_s, b_s)
+							DaisyError::IncompatibleUnits(a_s, b_s, None)
 						));
 					}
-					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, a.clone() - b.clone())));
+					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, a.clone() - match_units(context, a, b))));
 				}
 			}
 
 			return Ok(None);
 		},
 
+		Operator::PlusMinus => {
+			if args.len() != 2 { panic!() };
+			let a = &args[0];
+			let b = &args[1];
+
+			let Expression::Quantity(la, va) = a else { return Ok(None); };
+			let Expression::Quantity(lb, vb) = b else { return Ok(None); };
+
+			// A plain number takes the other side's unit,
+			// so `9.81 ± 0.02 m/s^2` is `(9.81 ± 0.02) m/s^2`.
+			let mut va = va.clone();
+			let mut vb = vb.clone();
+			if va.unitless() { va.set_unit(vb.unit.clone()); }
+			if vb.unitless() { vb.set_unit(va.unit.clone()); }
+
+			let Some(u) = vb.convert_to(va.clone(), context.get_rates()) else {
+				let a_s = va.convert_to_base(context.get_rates()).unit.display(context);
+				let b_s = vb.convert_to_base(context.get_rates()).unit.display(context);
+				return Err((*la + *lb + *op_loc, DaisyError::IncompatibleUnits(a_s, b_s, None)));
+			};
+
+			let Some(q) = va.with_uncertainty(u.scalar) else {
+				return Err((*lb, DaisyError::NegativeUncertainty));
+			};
+
+			return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, q)));
+		},
+
 
 		Operator::Divide |
 		Operator::DivideLong => {
@@ -161,7 +437,7 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 			if let Expression::Quantity(la, a) = a {
 				if let Expression::Quantity(lb, b) = b {
 					if b.is_zero() { return Err((*la + *lb + *op_loc, DaisyError::ZeroDivision)); }
-					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, a.clone() / b.clone())));
+					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, a.clone() / match_units(context, a, b))));
 				}
 			}
 
@@ -176,7 +452,7 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 
 			if let Expression::Quantity(la, a) = a {
 				if let Expression::Quantity(lb, b) = b {
-					let o = a.clone() * b.clone();
+					let o = a.clone() * match_units(context, a, b);
 					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, o)));
 				}
 			}
@@ -216,34 +492,22 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 			let a = &args[0];
 			let b = &args[1];
 
-			if let Expression::Quantity(la, va) = a {
-				if let Expression::Quantity(lb, vb) = b {
-					let n = va.clone().convert_to(vb.clone());
-					if n.is_none() {
-						let va = va.convert_to_base().unit;
-						let vb = vb.convert_to_base().unit;
+			let Expression::Quantity(la, va) = a else { return Ok(None); };
 
-						let a_s: String;
-						let b_s: String;
-						if va.unitless() {
-							a_s = String::from("scalar");
-						} else {
-							a_s = a.display(context);
-						}
-
-						if vb.unitless() {
-							b_s = String::from("scalar");
-						} else {
-							b_s = b.display(context);
-						}
+			// Convert to a list of units, like `5.3 ft to ft, in`
+			if let Expression::Tuple(lb, v) = b {
+				return Ok(Some(convert_to_parts(context, a, va, v, *la + *lb + *op_loc)?));
+			}
 
-						return Err((
-							*la + *lb + *op_loc,
-							DaisyError::IncompatibleUnits(a_s, b_s)
-						));
-					}
-					return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, n.unwrap())));
-				} else { return Ok(None); }
+			if let Expression::Quantity(lb, vb) = b {
+				let n = va.clone().convert_to(vb.clone(), context.get_rates());
+				if n.is_none() {
+					return Err((
+						*la + *lb + *op_loc,
+						incompatible_units(context, a, b)
+					));
+				}
+				return Ok(Some(Expression::Quantity(*la + *lb + *op_loc, n.unwrap())));
 			} else { return Ok(None); }
 		},
 
@@ -298,11 +562,20 @@ pub fn eval_operator(context: &mut Context, g: &Expression) -> Result<Option<Exp
 
 				let mut prod = Quantity::new_rational(1f64).unwrap();
 				let mut u = v.clone();
+				u.uncertainty = None;
 				while u > Quantity::new_rational(0f64).unwrap() {
 					prod *= u.clone();
 					u = u - Quantity::new_rational(1f64).unwrap();
 				}
 
+				// d/dn n! = n! (H(n) - γ), where H(n) is the nth harmonic number
+				if let Some(s) = &v.uncertainty {
+					let n = v.to_f64() as u64;
+					let h: f64 = (1..=n).map(|k| 1f64 / k as f64).sum();
+					let d = prod.to_f64() * (h - 0.5772156649015329);
+					prod.uncertainty = Some(s.clone() * Scalar::new_float(d.abs()).unwrap());
+				}
+
 				return Ok(Some(Expression::Quantity(*l + *op_loc, prod)));
 			} else { return Ok(None); }
 		}
@@ -0,0 +1,155 @@
+use std::sync::OnceLock;
+
+use crate::parser::Expression;
+use crate::parser::Constant;
+use crate::parser::Function;
+use crate::context::Context;
+use crate::quantity::Unit;
+use crate::quantity::FreeUnit;
+use crate::quantity::WholeUnit;
+use crate::quantity::Scalar;
+use crate::quantity::all_unit_strings;
+use crate::quantity::freeunit_from_string;
+
+use super::evaluate;
+
+
+// Edit distance between `a` and `b`, where
+// swapping two neighboring letters counts as one edit.
+// (This is the "optimal string alignment" distance.)
+fn edit_distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+
+	// d[i][j] is the distance between a[..i] and b[..j]
+	let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+	for i in 0..=a.len() { d[i][0] = i; }
+	for j in 0..=b.len() { d[0][j] = j; }
+
+	for i in 1..=a.len() {
+		for j in 1..=b.len() {
+			let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+
+			d[i][j] = (d[i - 1][j] + 1)
+				.min(d[i][j - 1] + 1)
+				.min(d[i - 1][j - 1] + cost);
+
+			if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+				d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+			}
+		}
+	}
+
+	return d[a.len()][b.len()];
+}
+
+
+// Names the user may have meant when they typed `s`.
+// Returns the closest few names, or nothing if none are close.
+pub fn suggest_names(context: &Context, s: &str) -> Vec<String> {
+	if !context.config.enable_suggestions { return Vec::new(); }
+	let n = s.chars().count();
+
+	// Almost everything is close to a very short name
+	if n < 3 { return Vec::new(); }
+	let max = if n <= 5 { 1 } else { 2 };
+
+	let mut v: Vec<String> = Vec::new();
+	v.extend(Function::all_strings().iter().map(|x| x.to_string()));
+	// Operators that look like words
+	v.extend(["sqrt", "mod"].iter().map(|x| x.to_string()));
+	v.extend(all_unit_strings().iter().map(|x| x.to_string()));
+	for c in Constant::all_consts() {
+		v.extend(c.source_strings().iter().map(|x| x.to_string()));
+	}
+	v.extend(context.get_variables().keys().cloned());
+	v.extend(context.get_functions().keys().cloned());
+	v.extend(context.get_native_functions().keys().cloned());
+
+	// Don't suggest names we can't print
+	v.retain(|x| context.config.enable_unicode || x.is_ascii());
+
+	let mut best: Vec<String> = Vec::new();
+	let mut best_d = max + 1;
+	for x in v {
+		let d = edit_distance(s, &x);
+		if d == 0 || d > best_d { continue; }
+		if d < best_d {
+			best_d = d;
+			best.clear();
+		}
+		if !best.contains(&x) { best.push(x); }
+	}
+
+	best.truncate(3);
+	return best;
+}
+
+
+// Every constant with units, and its base units.
+// Constants don't depend on the context we evaluate them in,
+// so this is only computed once.
+fn constant_units() -> &'static [(Constant, Unit)] {
+	static UNITS: OnceLock<Vec<(Constant, Unit)>> = OnceLock::new();
+
+	return UNITS.get_or_init(|| {
+		let mut context = Context::new();
+		let mut v = Vec::new();
+		for c in Constant::all_consts() {
+			let Ok(Expression::Quantity(_, q)) = evaluate(&mut context, &c.value()) else { continue };
+			if q.unit.unitless() { continue; }
+			v.push((*c, q.unit.to_base(None).unit));
+		}
+		return v;
+	});
+}
+
+// A well-known quantity with base units `u`, like `g₀` for accelerations.
+// We look for a constant first, then for a unit.
+fn example_of(context: &Context, u: &Unit) -> Option<String> {
+	if let Some((c, _)) = constant_units().iter().find(|(_, x)| x == u) {
+		return Some(if context.config.enable_unicode { c.to_string() } else { c.to_ascii_string() });
+	}
+
+	for w in WholeUnit::all() {
+		let f = FreeUnit::from_whole(*w);
+		if Unit::from_free(f).to_base(context.get_rates()).unit != *u { continue; }
+		return Some(if context.config.enable_unicode { f.to_string() } else { f.to_ascii_string() });
+	}
+
+	return None;
+}
+
+
+// How to turn a quantity in units `a` into one in units `b`,
+// like "multiply by an acceleration (e.g. g₀)" for `kg to N`.
+pub fn conversion_hint(context: &Context, a: &Unit, b: &Unit) -> Option<String> {
+	if !context.config.enable_suggestions { return None; }
+	let f = b.to_base(context.get_rates()).unit / a.to_base(context.get_rates()).unit;
+	if f.unitless() { return None; }
+
+	// Multiplying won't help a currency we have no rate for
+	if f.get_val().keys().any(|u| WholeUnit::rate_units().contains(&u.whole())) {
+		return None;
+	}
+
+	let i = f.pow(Scalar::new_rational(-1f64).unwrap());
+	let (verb, f) = if f.kind(context.get_rates()).is_none() && i.kind(context.get_rates()).is_some() { ("divide", i) } else { ("multiply", f) };
+
+	let Some(k) = f.kind(context.get_rates()) else {
+		// Base units are in grams, but kilograms are easier to read
+		let mut u = Unit::new();
+		for (x, p) in f.get_val() {
+			if x.whole() == WholeUnit::Gram {
+				u.insert(freeunit_from_string("kg").unwrap(), p.clone());
+			} else { u.insert(*x, p.clone()); }
+		}
+		return Some(format!("{verb} by something in {}", u.display(context)));
+	};
+
+	let article = if k.starts_with(['a', 'e', 'i', 'o', 'u']) { "an" } else { "a" };
+	return Some(match example_of(context, &f) {
+		Some(e) => format!("{verb} by {article} {k} (e.g. {e})"),
+		None => format!("{verb} by {article} {k}")
+	});
+}
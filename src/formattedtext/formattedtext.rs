@@ -22,6 +22,72 @@ impl FormattedText {
 	pub fn push(&mut self, s: &str) {
 		self.text.push_str(s);
 	}
+
+	// Splits this text into plain text and formatting tags.
+	// Tags are returned without their brackets, text as-is.
+	// Unknown tags are text, just like the terminal backends do.
+	fn parts(&self) -> Vec<(bool, &str)> {
+		let mut out = Vec::new();
+		let mut rest = &self.text[..];
+
+		while let Some(a) = rest.find('[') {
+			out.push((false, &rest[..a]));
+			rest = &rest[a..];
+
+			let Some(b) = rest[1..].find(|c| c == '[' || c == ']') else { break };
+			let b = b + 1;
+
+			if &rest[b..b+1] == "[" {
+				// Not a tag, start again at the next bracket.
+				out.push((false, &rest[..b]));
+				rest = &rest[b..];
+				continue;
+			}
+
+			let tag = &rest[1..b];
+			let known = match tag {
+				"n"|"i"|"t"|"a"|
+				"e"|"c"|"s"|"r"|
+				"p"|"d"|"clear"|
+				"hnum"|"hunit"|"hconst"|"hfunc"|
+				"hufunc"|"hvar"|"hop"|"herr"
+				=> true,
+				_ => tag.starts_with("cursorright") || tag.starts_with("cursorup")
+			};
+
+			if known { out.push((true, tag)); } else { out.push((false, &rest[..b+1])); }
+			rest = &rest[b+1..];
+		}
+
+		out.push((false, rest));
+		return out;
+	}
+
+	// Returns this text with all formatting tags removed.
+	pub fn plain(&self) -> String {
+		return self.parts().into_iter()
+			.filter(|(t, _)| !t)
+			.map(|(_, s)| s)
+			.collect();
+	}
+
+	// Returns the last line of this text.
+	// If a style is still active where that line starts
+	// (like `[herr]a\nb[n]`), the line starts with that style too.
+	pub fn last_line(&self) -> FormattedText {
+		let Some(i) = self.text.rfind('\n') else { return self.clone(); };
+		let head = FormattedText::new(self.text[..i].to_string());
+
+		// The last style tag before this line, unless it was reset
+		let mut style: Option<&str> = None;
+		for (t, s) in head.parts() {
+			if !t || s == "clear" || s.starts_with("cursor") { continue; }
+			style = if s == "n" { None } else { Some(s) };
+		}
+
+		let style = style.map(|s| format!("[{s}]")).unwrap_or_default();
+		return FormattedText::new(format!("{style}{}", &self.text[i+1..]));
+	}
 }
 
 
@@ -2,7 +2,6 @@ use super::FormattedText;
 use std::io::Write;
 use crate::context::Context;
 
-use termion::raw::RawTerminal;
 use termion::color;
 use termion::style;
 use termion::clear;
@@ -37,6 +36,32 @@ fn format_map_ansi(s: &str) -> Option<String> {
 		"r" => { // Result prompt (how = is styled) (should be green)
 			format!("{}{}", color::Fg(color::AnsiValue(2)), color::Bg(color::Reset))
 		},
+		"d" => { // Live preview (no dim style in 8 colors)
+			format!("{}{}", color::Fg(color::Reset), color::Bg(color::Reset))
+		},
+
+		// Syntax highlighting
+		"hnum" => { // Numbers (should be cyan)
+			format!("{}{}", color::Fg(color::AnsiValue(6)), color::Bg(color::Reset))
+		},
+		"hunit" => { // Units (should be green)
+			format!("{}{}", color::Fg(color::AnsiValue(2)), color::Bg(color::Reset))
+		},
+		"hconst" => { // Constants (should be yellow)
+			format!("{}{}", color::Fg(color::AnsiValue(3)), color::Bg(color::Reset))
+		},
+		"hfunc" | "hufunc" => { // Functions (should be blue)
+			format!("{}{}", color::Fg(color::AnsiValue(4)), color::Bg(color::Reset))
+		},
+		"hvar" => { // Variables (should be pink)
+			format!("{}{}", color::Fg(color::AnsiValue(5)), color::Bg(color::Reset))
+		},
+		"hop" => { // Operators
+			format!("{}{}", color::Fg(color::Reset), color::Bg(color::Reset))
+		},
+		"herr" => { // Undefined names, unmatched parenthesis (should be red)
+			format!("{}{}", color::Fg(color::AnsiValue(1)), color::Bg(color::Reset))
+		},
 
 		_ => { return None }
 	})
@@ -48,7 +73,9 @@ fn format_map_none(s: &str) -> Option<String> {
 	Some(match s {
 		"n"|"i"|"t"|"a"|
 		"e"|"c"|"s"|"r"|
-		"p"
+		"p"|"d"|
+		"hnum"|"hunit"|"hconst"|"hfunc"|
+		"hufunc"|"hvar"|"hop"|"herr"
 		=> { "".to_string() },
 		_ => { return None }
 	})
@@ -85,6 +112,35 @@ fn format_map_full(s: &str) -> Option<String> {
 		"r" => { // Result prompt (how = is styled)
 			format!("{}{}", color::Fg(color::Green), style::Bold)
 		},
+		"d" => { // Live preview
+			format!("{}{}", color::Fg(color::LightBlack), style::Faint)
+		},
+
+		// Syntax highlighting
+		"hnum" => { // Numbers
+			format!("{}{}", style::Reset, color::Fg(color::Cyan))
+		},
+		"hunit" => { // Units
+			format!("{}{}", style::Reset, color::Fg(color::Green))
+		},
+		"hconst" => { // Constants
+			format!("{}{}", style::Reset, color::Fg(color::Yellow))
+		},
+		"hfunc" => { // Built-in functions
+			format!("{}{}", style::Reset, color::Fg(color::Blue))
+		},
+		"hufunc" => { // User functions
+			format!("{}{}", style::Reset, color::Fg(color::LightBlue))
+		},
+		"hvar" => { // Variables
+			format!("{}{}", style::Reset, color::Fg(color::Magenta))
+		},
+		"hop" => { // Operators
+			format!("{}{}", style::Reset, style::Bold)
+		},
+		"herr" => { // Undefined names, unmatched parenthesis
+			format!("{}{}{}", style::Reset, color::Fg(color::Red), style::Underline)
+		},
 
 
 		_ => { return None }
@@ -92,7 +148,7 @@ fn format_map_full(s: &str) -> Option<String> {
 }
 
 impl FormattedText {
-	pub fn newline(stdout: &mut RawTerminal<std::io::Stdout>) -> Result<(), std::io::Error> {
+	pub fn newline(stdout: &mut impl Write) -> Result<(), std::io::Error> {
 		write!(stdout, "\n")?;
 		return Ok(());
 	}
@@ -106,7 +162,7 @@ impl FormattedText {
 		}
 	}
 
-	pub fn write(&self, context: &Context, stdout: &mut RawTerminal<std::io::Stdout>) -> Result<(), std::io::Error> {
+	pub fn write(&self, context: &Context, stdout: &mut impl Write) -> Result<(), std::io::Error> {
 
 		let mut word = String::new();
 		let mut reading = false; // are we reading a word?
@@ -151,6 +207,12 @@ impl FormattedText {
 								"{}",
 								cursor::Right(n),
 							));
+						} else if word.starts_with("[cursorup") {
+							let n: u16 = word[9..word.len()-1].parse().unwrap();
+							out.push_str(&format!(
+								"{}",
+								cursor::Up(n),
+							));
 						} else {
 							out.push_str(&word);
 						}
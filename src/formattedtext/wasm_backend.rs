@@ -11,6 +11,17 @@ fn format_map(s: &str) -> Option<String> {
 		"p" => {"\x1B[1;34m"},
 		"s" => {"\x1B[1;35m"},
 		"r" => {"\x1B[1;32m"},
+		"d" => {"\x1B[2;90m"},
+
+		// Syntax highlighting
+		"hnum" => {"\x1B[0;36m"},
+		"hunit" => {"\x1B[0;32m"},
+		"hconst" => {"\x1B[0;33m"},
+		"hfunc" => {"\x1B[0;34m"},
+		"hufunc" => {"\x1B[0;94m"},
+		"hvar" => {"\x1B[0;35m"},
+		"hop" => {"\x1B[0;1m"},
+		"herr" => {"\x1B[0;4;31m"},
 		_ => { return None }
 	}.to_string())
 }
@@ -54,6 +65,9 @@ impl FormattedText {
 						} else if word.starts_with("[cursorright") {
 							let n: u16 = word[12..word.len()-1].parse().unwrap();
 							out.push_str(&format!("\x1B[{n}C"));
+						} else if word.starts_with("[cursorup") {
+							let n: u16 = word[9..word.len()-1].parse().unwrap();
+							out.push_str(&format!("\x1B[{n}A"));
 						} else {
 							out.push_str(&word);
 						}
@@ -2,8 +2,10 @@ pub mod parser;
 pub mod command;
 pub mod quantity;
 
-use crate::parser::substitute;
 use crate::parser::LineLocation;
+use crate::parser::Statement;
+
+use unicode_width::UnicodeWidthChar;
 
 
 mod context;
@@ -11,12 +13,16 @@ mod formattedtext;
 mod errors;
 mod evaluate;
 mod promptbuffer;
+mod calculator;
 
 pub use crate::formattedtext::FormattedText;
 pub use crate::context::Context;
+pub use crate::context::NativeFunction;
 pub use crate::errors::DaisyError;
 pub use crate::evaluate::evaluate;
+pub use crate::evaluate::evaluate_variable;
 pub use crate::promptbuffer::PromptBuffer;
+pub use crate::calculator::{Calculator, Value, Error};
 
 
 
@@ -62,7 +68,7 @@ cfg_if::cfg_if! {
 					out += t;
 
 
-					let in_str = unsafe { (*state).promptbuffer.enter() };
+					let in_str = unsafe { (*state).promptbuffer.enter(&(*state).context) };
 					out += FormattedText::new("\n".to_string());
 					if in_str == "" {
 						return format!("\r\n{}", daisy_prompt(state));
@@ -84,20 +90,42 @@ cfg_if::cfg_if! {
 				"\x1B[C" => { unsafe { (*state).promptbuffer.cursor_right(); } },
 				"\x1B[A" => { unsafe { (*state).promptbuffer.hist_up(); } },
 				"\x1B[B" => { unsafe { (*state).promptbuffer.hist_down(); } },
+				"\t" => { unsafe { (*state).promptbuffer.complete(&(*state).context); } },
+
+				"\x1B[H" | "\x1BOH" | "\x01" => { unsafe { (*state).promptbuffer.cursor_home(); } },
+				"\x1B[F" | "\x1BOF" | "\x05" => { unsafe { (*state).promptbuffer.cursor_end(); } },
+				"\x1B[1;5D" | "\x1Bb" => { unsafe { (*state).promptbuffer.word_left(); } },
+				"\x1B[1;5C" | "\x1Bf" => { unsafe { (*state).promptbuffer.word_right(); } },
+
+				"\x17" => { unsafe { (*state).promptbuffer.kill_word(); } },
+				"\x15" => { unsafe { (*state).promptbuffer.kill_start(); } },
+				"\x0B" => { unsafe { (*state).promptbuffer.kill_end(); } },
+				"\x19" => { unsafe { (*state).promptbuffer.yank(); } },
+
+				"\x12" => { unsafe { (*state).promptbuffer.search(); } },
+				"\x07" => { unsafe { (*state).promptbuffer.cancel_search(); } },
+
+				"\x0C" => { out += FormattedText::new("[clear]".to_string()); },
 
 				//'\x04' | '\x03'
 				//=> { break 'outer; },
 
-				// Only process sane characters
-
 				_ => {
-					let c = s.chars().next().unwrap();
-					match c {
-						'a'..='z' | 'A'..='Z' | '0'..='9'
-						|'!'|'@'|'#'|'$'|'%'|'^'|'&'|'*'|'('|')'
-						|'?'|'~'|','|'.'|'['|']'|' '
-						|'<'|'>'|'/'|'_'|'-'|':'|'|'|'='|'+'|';'
-						=> { unsafe { (*state).promptbuffer.add_char(c); } },
+					// Pasted text arrives all at once,
+					// and may be wrapped in bracketed paste markers.
+					let p = s.strip_prefix("\x1B[200~").unwrap_or(&s);
+					let p = p.strip_suffix("\x1B[201~").unwrap_or(p);
+
+					let mut chars = p.chars();
+					match (chars.next(), chars.next()) {
+						// Accept any printable character
+						(Some(c), None) if !c.is_control() => {
+							unsafe { (*state).promptbuffer.add_char(c); }
+						},
+
+						(Some(_), Some(_)) if !p.starts_with('\x1B') => {
+							unsafe { (*state).promptbuffer.paste(p); }
+						},
 
 						_ => {}
 					}
@@ -116,38 +144,58 @@ pub fn do_string(
 	s: &String
 ) -> Result<FormattedText, FormattedText> {
 
-	let r: (LineLocation, DaisyError);
 	if command::is_command(s) {
 		return Ok(command::do_command(context, s));
-	} else if s.contains("=") {
-		let x = do_assignment(context, s);
-		match x {
-			Ok(t) => { return Ok(t) },
-			Err(t) => { r = t }
-		};
-	} else {
-		let x = do_expression(context, s);
-		match x {
-			Ok((t, e)) => { context.push_hist(e); return Ok(t) },
-			Err(t) => { r = t }
-		};
 	}
 
-	let (l, e) = r;
+	let (l, e) = match do_statement(context, s) {
+		Ok(t) => { return Ok(t) },
+		Err(r) => r
+	};
+
 	let mut t = FormattedText::new("".to_string());
 	if l.zero() {
 		t.push(&format!(
 			"\n  {}\n\n",
 			e.text().to_string(),
 		));
+	} else if s.contains('\n') {
+		// Multi-line input. Linelocations count from the start
+		// of the first line, and newlines are one character.
+		// Reprint the line with the error so we can point at it.
+		let s = parser::substitute(context, s);
+		let mut start = 0;
+		for line in s.split('\n') {
+			let n = line.chars().count();
+			if l.pos <= start + n {
+				let pos = l.pos - start;
+				let len = l.len.min(n - pos).max(1);
+				t.push(&format!(
+					concat!(
+						"    {}\n",
+						"{}[e]{}[n]\n",
+						"  {}\n\n"
+					),
+					line,
+					" ".repeat(columns(line, 0, pos) + 4),
+					"^".repeat(columns(line, pos, pos + len).max(1)),
+					e.text().to_string(),
+				));
+				break;
+			}
+			start += n + 1;
+		}
 	} else {
+		// The prompt shows substituted input,
+		// and wide characters take two columns.
+		let s = parser::substitute(context, s);
 		t.push(&format!(
 			concat!(
 				"{}[e]{}[n]\n",
 				"  {}\n\n"
 			),
-			" ".repeat(l.pos + 4),
-			"^".repeat(l.len),
+			" ".repeat(columns(&s, 0, l.pos) + 4),
+			"^".repeat(columns(&s, l.pos, l.pos + l.len).max(1)),
 			e.text().to_string(),
 		));
 	}
@@ -155,221 +203,75 @@ pub fn do_string(
 	return Err(t);
 }
 
-// Handle a simple evaluation string.
-// Returns a FormattedText with output that should be printed.
-#[inline(always)]
-fn do_expression(
-	context: &mut Context,
-	s: &String
-) -> Result<(FormattedText, parser::Expression), (LineLocation, DaisyError)> {
-
-	let mut output = FormattedText::new("".to_string());
-
-	let g = parser::parse(context, &s)?;
-	let g_evaluated = evaluate::evaluate(context, &g)?;
-
-	// Display parsed string
-	output.push(&format!(
-		" [s]=>[n] {}\n\n",
-		g.display(context)
-	));
-
-	// Display result
-	output.push(&format!(
-		"  [r]=[n] {}\n\n",
-		g_evaluated.display_outer(context),
-	));
-
-	return Ok((output, g_evaluated));
+// How many terminal columns chars a..b of `s` take up.
+// Positions past the end of `s` are one column each.
+fn columns(s: &str, a: usize, b: usize) -> usize {
+	let n = s.chars().count();
+	let w: usize = s.chars()
+		.skip(a).take(b.saturating_sub(a))
+		.map(|c| c.width().unwrap_or(0))
+		.sum();
+	return w + b.saturating_sub(n.max(a));
 }
 
-
-// Handle a variable or function definition string.
+// Handle an expression, variable definition, or function definition.
 // Returns a FormattedText with output that should be printed.
 #[inline(always)]
-fn do_assignment(
+fn do_statement(
 	context: &mut Context,
 	s: &String
 ) -> Result<FormattedText, (LineLocation, DaisyError)> {
 
 	let mut output = FormattedText::new("".to_string());
 
-	let parts = s.split("=").collect::<Vec<&str>>();
-	if parts.len() != 2 {
-		return Err((
-			LineLocation::new_zero(),
-			DaisyError::Syntax
-		));
-	}
-
-	// Index of first non-whitespace character in left
-	// (relative to whole prompt)
-	let starting_left = parts[0]
-		.char_indices()
-		.find(|(_, ch)| !(ch.is_whitespace() && *ch != '\n'))
-		.map(|(i, _)| i)
-		.unwrap_or_else(|| parts[0].len());
-
-	// Index of first non-whitespace character in right
-	// (relative to whole prompt)
-	// +1 accounts for equals sign
-	let starting_right = parts[0].chars().count() + 1 +
-		parts[1]
-			.char_indices()
-			.find(|(_, ch)| !(ch.is_whitespace() && *ch != '\n'))
-			.map(|(i, _)| i)
-			.unwrap_or_else(|| parts[0].len());
-
-
-	let left = substitute(context, &parts[0].trim().to_string());
-	let right = substitute(context, &parts[1].trim().to_string());
-	let is_function = left.contains("(");
-
-	// The order of methods below is a bit odd.
-	// This is intentional, since we want to check a definition's
-	// variable name before even attempting to parse its content.
-	if is_function {
-		let mut mode = 0;
-		let mut name = String::new();
-		let mut args = String::new();
-		for c in left.chars() {
-			match mode {
-
-				// Mode 0: reading function name
-				0 => {
-					if c == '(' {
-						mode = 1; continue;
-					} else { name.push(c); }
-				},
-
-				// Mode 1: reading arguments
-				1 => {
-					if c == ')' {
-						mode = 2; continue;
-					} else { args.push(c); }
-				},
-
-				// Mode 2: we should be done by now.
-				// That close paren should've been the last character.
-				2 => {
-					return Err((
-						LineLocation{ pos: starting_left, len: left.chars().count() },
-						DaisyError::Syntax
-					));
-				},
-
-				_ => unreachable!()
-			}
-		}
-
+	match parser::parse_statement(context, s)? {
+		Statement::Expression(g) => {
+			let g_evaluated = evaluate::evaluate(context, &g)?;
 
-		let args = args
-			.split(",").collect::<Vec<&str>>()
-			.iter().map(|x| x.trim().to_string()).collect::<Vec<String>>();
-
-		if name.len() == 0 {
-			return Err((
-				LineLocation{ pos: starting_left, len: left.chars().count() },
-				DaisyError::Syntax
+			// Display parsed string
+			output.push(&format!(
+				" [s]=>[n] {}\n\n",
+				g.display(context)
 			));
-		};
 
-		if !context.valid_function(&name) {
-			return Err((
-				LineLocation{ pos: starting_left, len: left.chars().count() },
-				DaisyError::BadFunction
+			// Display result, numbered so it may be referenced later
+			let r = g_evaluated.display_outer(context);
+			// Multi-line input is saved as one line
+			let n = context.push_hist(s.trim().replace('\n', " "), g_evaluated);
+			output.push(&format!(
+				"  [r]${n} =[n] {r}\n\n",
 			));
-		};
+		},
 
-		if args.iter().find(|x| &x[..] == "").is_some() {
-			return Err((
-				LineLocation{ pos: starting_left, len: left.chars().count() },
-				DaisyError::Syntax
-			));
-		};
+		Statement::VariableDefinition(_, name, g) => {
+			let g_evaluated = evaluate::evaluate_variable(context, &name, &g)?;
 
-		for a in &args {
-			if !context.valid_varible(a) {
-				return Err((
-					LineLocation{ pos: starting_left, len: left.chars().count() },
-					DaisyError::BadVariable
-				));
-			}
-		}
-
-		// Parse right hand side
-		let g = parser::parse(context, &right);
-		let Ok(g) = g else {
-			let Err((l, e)) = g else { unreachable!() };
-			return Err((
-				LineLocation{ pos: l.pos + starting_right, len: l.len},
-				e
+			// Display parsed string
+			output.push(&format!(
+				" [t]=>[n] {name} = {}\n\n",
+				g.display(context)
 			));
-		};
 
-		// Display parsed string
-		output.push(&format!(
-			" [s]=>[n] {left} = {}\n\n",
-			g.display(context)
-		));
+			context.push_variable(name, g_evaluated).unwrap();
+		},
 
-		// Evaluate expression with shadow variables
-		for a in &args { context.add_shadow(a.to_string(), None);}
-		let g_evaluated = evaluate::evaluate(context, &g);
-		context.clear_shadow();
-		let Ok(_g_evaluated) = g_evaluated else {
-			let Err((l, e)) = g_evaluated else { unreachable!() };
-			return Err((
-				LineLocation{ pos: l.pos + starting_right, len: l.len},
-				e
-			));
-		};
+		Statement::FunctionDefinition(_, name, args, g) => {
 
-		// We could push g_evaluated instead, but an un-evaluated string
-		// makes the 'vars' command prettier.
-		//
-		// We still need to evaluate g above, though, to make sure it works.
-		context.push_function(name, args, g).unwrap();
-	} else {
+			// Make sure this function works
+			evaluate::check_function(context, &name, &args, &g)?;
 
-		if !context.valid_varible(&left) {
-			return Err((
-				LineLocation{ pos: starting_left, len: left.chars().count() },
-				DaisyError::BadVariable
+			// Display parsed string
+			output.push(&format!(
+				" [s]=>[n] {name}({}) = {}\n\n",
+				args.join(", "),
+				g.display(context)
 			));
-		}
-
-		// Parse right hand side
-		let g = parser::parse(context, &right);
-		let Ok(g) = g else {
-			let Err((l, e)) = g else { unreachable!() };
-			return Err((
-				LineLocation{ pos: l.pos + starting_right, len: l.len},
-				e
-			));
-		};
-
-		// Display parsed string
-		output.push(&format!(
-			" [t]=>[n] {left} = {}\n\n",
-			g.display(context)
-		));
 
-		// Evaluate expression
-		let g_evaluated = evaluate::evaluate(context, &g);
-		let Ok(g_evaluated) = g_evaluated else {
-			let Err((l, e)) = g_evaluated else { unreachable!() };
-			return Err((
-				LineLocation{ pos: l.pos + starting_right, len: l.len},
-				e
-			));
-		};
-
-		context.push_variable(left.to_string(), g_evaluated).unwrap();
+			// We could push g_evaluated instead, but an un-evaluated string
+			// makes the 'vars' command prettier.
+			context.push_function(name, args, g).unwrap();
+		}
 	}
 
 	return Ok(output);
-
 }
-
-
@@ -1,12 +1,15 @@
 use std::io::stdout;
 use std::io::stdin;
+use std::io::Write;
+use std::io::BufRead;
 use std::env;
+use std::path::PathBuf;
 
 use termion::{
 	event::Key,
+	event::Event,
 	input::TermRead,
-	raw::IntoRawMode,
-	color::DetectColors
+	raw::IntoRawMode
 };
 
 use daisycalc::PromptBuffer;
@@ -14,73 +17,155 @@ use daisycalc::command;
 use daisycalc::Context;
 use daisycalc::FormattedText;
 use daisycalc::do_string;
+use daisycalc::parser;
+
+mod terminal;
+mod rates;
 
 #[cfg(test)]
 mod tests;
 
 
 
+// Write `t` to stdout.
+// If stdout isn't a terminal, write plain text without escape codes.
+fn print(context: &Context, t: &FormattedText, tty: bool) -> Result<(), std::io::Error> {
+	let mut stdout = stdout();
+	if tty { return t.write(context, &mut stdout); }
+	write!(stdout, "{}", t.plain())?;
+	return stdout.flush();
+}
+
+// Evaluate each line of `input`, writing plain text to `out`.
+// Used when stdout isn't a terminal, so we can't draw a prompt.
+fn run_lines(context: &mut Context, input: impl BufRead, out: &mut impl Write) -> Result<(), std::io::Error> {
+	for l in input.lines() {
+		let l = l?;
+		if l.trim() == "" { continue; }
+		if l.trim() == "quit" { break; }
+
+		match do_string(context, &l) {
+			Ok(t) => { write!(out, "{}", t.plain())?; },
+
+			// Errors point at the input line,
+			// which isn't on screen without a prompt.
+			// Carets count columns in the substituted line, so print that.
+			Err(t) => {
+				let s = parser::substitute(context, &l);
+				write!(out, "==> {s}\n{}", t.plain())?;
+			}
+		}
+	}
+	return out.flush();
+}
+
+
 #[inline(always)]
 pub fn main() -> Result<(), std::io::Error> {
-	let mut stdout = stdout().into_raw_mode().unwrap();
-	let mut pb: PromptBuffer = PromptBuffer::new(64);
 	let mut context = Context::new();
 
-	// Detect color compatibilty
-	// Currently unused, this is slow.
-	/*
-	let term_colors = stdout.available_colors().unwrap_or(0);
-	if term_colors >= 256 {
-		context.config.term_color_type = 2;
-	} else if term_colors >= 8 {
-		context.config.term_color_type = 1;
-	} else {
-		context.config.term_color_type = 0;
+	let var = |x: &str| env::var(x).ok();
+	let tty = termion::is_tty(&stdout());
+	context.config.term_color_type = terminal::color_type(&var, tty);
+	context.config.enable_unicode = terminal::locale_is_unicode(&var);
+
+	// Handle command-line arguments.
+	// Later flags override earlier ones.
+	let mut show: Option<String> = None;
+	let mut rates_file: Option<PathBuf> = None;
+	for a in env::args().skip(1) {
+		match &a[..] {
+			"--help" | "--version" | "--info" => { show.get_or_insert(a); },
+			"--256color" => { context.config.term_color_type = 2; },
+			"--8color" => { context.config.term_color_type = 1; },
+			"--nocolor" | "--0color" => { context.config.term_color_type = 0; },
+			"--nosub" => { context.config.enable_substituion = false; },
+			"--nosuper" => { context.config.enable_super_powers = false; },
+			"--nooneover" => { context.config.enable_one_over_power = false; },
+			"--nosimplify" => { context.config.enable_simplify = false; },
+			"--ascii" => { context.config.enable_unicode = false; },
+			"--unicode" => { context.config.enable_unicode = true; },
+			_ if a.starts_with("--rates=") => { rates_file = Some(PathBuf::from(&a[8..])); },
+			_ => {
+				let t = FormattedText::new(format!(
+					"[e]Unknown flag[n] [c]{a}[n][e]. Run [n][c]daisy --help[n][e] for a list of flags.[n]\n"
+				));
+				print(&context, &t, tty)?;
+				return Ok(());
+			}
+		}
 	}
-	*/
 
+	context.config.check();
 
-	// Handle command-line arguments
-	let args: Vec<String> = env::args().collect();
-	if args.iter().any(|s| s == "--help") {
-		let t = command::do_command(&mut context, &String::from("help"));
-		t.write(&context, &mut stdout)?;
-		let t = command::do_command(&mut context, &String::from("flags"));
-		t.write(&context, &mut stdout)?;
-		return Ok(());
-	} else if args.iter().any(|s| s == "--version") {
-		let t = FormattedText::new(format!(
-			"Daisy v{}\n", env!("CARGO_PKG_VERSION")
-		));
-		t.write(&context, &mut stdout)?;
-		return Ok(());
-	} else if args.iter().any(|s| s == "--info") {
-		let t = FormattedText::new(format!(
-			concat!(
-				"Daisy v{}\n",
-				"Your terminal supports {} colors.\n"
-			),
-			env!("CARGO_PKG_VERSION"),
-			stdout.available_colors().unwrap_or(0)
-		));
-		t.write(&context, &mut stdout)?;
-		return Ok(());
-	} else if args.iter().any(|s| s == "--256color") {
-		context.config.term_color_type = 2;
-	} else if args.iter().any(|s| s == "--8color") {
-		context.config.term_color_type = 1;
-	} else if args.iter().any(|s| s == "--0color") {
-		context.config.term_color_type = 0;
-	} else if args.iter().any(|s| s == "--nosub") {
-		context.config.enable_substituion = false;
-	} else if args.iter().any(|s| s == "--nosuper") {
-		context.config.enable_super_powers = false;
-	} else if args.iter().any(|s| s == "--nooneover") {
-		context.config.enable_one_over_power = false;
+	match show.as_deref() {
+		Some("--help") => {
+			let t = command::do_command(&mut context, &String::from("help"));
+			print(&context, &t, tty)?;
+			let t = command::do_command(&mut context, &String::from("flags"));
+			print(&context, &t, tty)?;
+			return Ok(());
+		},
+
+		Some("--version") => {
+			let t = FormattedText::new(format!(
+				"Daisy v{}\n", env!("CARGO_PKG_VERSION")
+			));
+			print(&context, &t, tty)?;
+			return Ok(());
+		},
+
+		Some("--info") => {
+			let t = FormattedText::new(format!(
+				concat!(
+					"Daisy v{}\n",
+					"Colors: {}\n",
+					"Unicode: {}\n"
+				),
+				env!("CARGO_PKG_VERSION"),
+				match context.config.term_color_type {
+					2 => "256 colors and styles",
+					1 => "8 colors",
+					_ => "none"
+				},
+				if context.config.enable_unicode { "yes" } else { "no" }
+			));
+			print(&context, &t, tty)?;
+			return Ok(());
+		},
+
+		_ => {}
 	}
 
-	context.config.check();
+	// Load exchange rates.
+	// It's fine if the default file doesn't exist.
+	let explicit = rates_file.is_some();
+	if let Some(p) = rates_file.or_else(|| rates::default_path(&var)) {
+		if explicit || p.exists() {
+			match rates::load(&p) {
+				Ok(r) => { context.set_rates(Some(r)); },
+				Err(e) => {
+					let t = FormattedText::new(format!(
+						"[e]Could not read exchange rates from[n] [c]{}[n][e]: {e}[n]\n",
+						p.display()
+					));
+					print(&context, &t, tty)?;
+					if explicit { return Ok(()); }
+				}
+			}
+		}
+	}
+
+	if !tty { return run_lines(&mut context, stdin().lock(), &mut stdout()); }
+
+	let mut stdout = stdout().into_raw_mode()?;
+	let mut pb: PromptBuffer = PromptBuffer::new(64);
+
 
+	// Enable bracketed paste, so pasted text
+	// isn't mistaken for key presses.
+	write!(stdout, "\x1B[?2004h")?;
+	let mut paste: Option<String> = None;
 
 	'outer: loop {
 
@@ -88,8 +173,34 @@ pub fn main() -> Result<(), std::io::Error> {
 		t.write(&context, &mut stdout)?;
 
 		let stdin = stdin();
-		for c in stdin.keys() {
-			if let Key::Char(q) = c.as_ref().unwrap() {
+		for e in stdin.events() {
+			let c = match e? {
+				Event::Key(k) => k,
+
+				// termion doesn't parse Ctrl-Left and Ctrl-Right.
+				// These work like Alt-B and Alt-F.
+				Event::Unsupported(b) if b == b"\x1B[1;5D" => Key::Alt('b'),
+				Event::Unsupported(b) if b == b"\x1B[1;5C" => Key::Alt('f'),
+
+				// Start and end of bracketed paste
+				Event::Unsupported(b) if b == b"\x1B[200~" => {
+					paste = Some(String::new());
+					continue;
+				},
+				Event::Unsupported(b) if b == b"\x1B[201~" => {
+					if let Some(s) = paste.take() { pb.paste(&s); }
+					Key::Null
+				},
+
+				_ => continue
+			};
+
+			if let Some(s) = &mut paste {
+				if let Key::Char(q) = c { s.push(q); }
+				continue;
+			}
+
+			if let Key::Char(q) = &c {
 				match q {
 					'\n' => {
 						// Print again without cursor, in case we pressed enter
@@ -98,7 +209,7 @@ pub fn main() -> Result<(), std::io::Error> {
 						t.write(&context, &mut stdout)?;
 
 
-						let in_str = pb.enter();
+						let in_str = pb.enter(&context);
 						FormattedText::newline(&mut stdout)?;
 						if in_str == "" { break; }
 
@@ -117,17 +228,15 @@ pub fn main() -> Result<(), std::io::Error> {
 						break;
 					},
 
-					// Only process sane characters
-					'a'..='z' | 'A'..='Z' | '0'..='9'
-					|'!'|'@'|'#'|'$'|'%'|'^'|'&'|'*'|'('|')'
-					|'?'|'~'|','|'.'|'['|']'|' '
-					|'<'|'>'|'/'|'_'|'-'|':'|'|'|'='|'+'|';'
-					=> { pb.add_char(*q); },
+					'\t' => { pb.complete(&context); },
+
+					// Accept any printable character
+					_ if !q.is_control() => { pb.add_char(*q); },
 
 					_ => {}
 				};
 			} else {
-				match c.unwrap() {
+				match c {
 					Key::Backspace => { pb.backspace(); },
 					Key::Delete => { pb.delete(); },
 					Key::Left => { pb.cursor_left(); },
@@ -135,8 +244,31 @@ pub fn main() -> Result<(), std::io::Error> {
 					Key::Up => { pb.hist_up(); },
 					Key::Down => { pb.hist_down(); },
 
+					Key::Home | Key::Ctrl('a') => { pb.cursor_home(); },
+					Key::End | Key::Ctrl('e') => { pb.cursor_end(); },
+					Key::Alt('b') => { pb.word_left(); },
+					Key::Alt('f') => { pb.word_right(); },
+
+					Key::Ctrl('w') => { pb.kill_word(); },
+					Key::Ctrl('u') => { pb.kill_start(); },
+					Key::Ctrl('k') => { pb.kill_end(); },
+					Key::Ctrl('y') => { pb.yank(); },
+
+					Key::Ctrl('r') => { pb.search(); },
+					Key::Ctrl('g') => { pb.cancel_search(); },
+
+					Key::Ctrl('l') => {
+						let t = FormattedText::new("[clear]".to_string());
+						t.write(&context, &mut stdout)?;
+					},
+
 					Key::Ctrl('d') |
-					Key::Ctrl('c') => { break 'outer; },
+					Key::Ctrl('c') => {
+						// Clear the preview before leaving
+						let t = pb.write_prompt_nocursor(&mut context);
+						t.write(&context, &mut stdout)?;
+						break 'outer;
+					},
 					_ => {}
 				};
 			};
@@ -146,6 +278,7 @@ pub fn main() -> Result<(), std::io::Error> {
 		}
 	}
 
+	write!(stdout, "\x1B[?2004l")?;
 	FormattedText::newline(&mut stdout)?;
 	return Ok(());
 }
\ No newline at end of file
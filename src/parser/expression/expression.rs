@@ -33,13 +33,17 @@ pub enum Expression {
 	Constant(LineLocation, Constant),
 	Operator(LineLocation, Operator, VecDeque<Expression>),
 	Tuple(LineLocation, VecDeque<Expression>),
+	Boolean(LineLocation, bool),
 }
 
 impl Expression {
 	pub fn display(&self, context: &Context) -> String {
 		match self {
 			Expression::Quantity(_, v) => v.display(context),
-			Expression::Constant(_, c) => c.to_string(),
+			Expression::Boolean(_, b) => b.to_string(),
+			Expression::Constant(_, c) => {
+				if context.config.enable_unicode { c.to_string() } else { c.to_ascii_string() }
+			},
 			Expression::Variable(_, s) => s.clone(),
 			Expression::Operator(_, o,a) => o.display(context, a),
 			Expression::Tuple(_, v) => {
@@ -60,13 +64,16 @@ impl Expression {
 	pub fn display_outer(&self, context: &Context) -> String {
 		match self {
 			Expression::Quantity(_, v) => v.display_outer(context),
-			Expression::Constant(_, c) => c.to_string(),
+			Expression::Boolean(_, b) => b.to_string(),
+			Expression::Constant(_, c) => {
+				if context.config.enable_unicode { c.to_string() } else { c.to_ascii_string() }
+			},
 			Expression::Variable(_, s) => s.clone(),
 			Expression::Operator(_, o,a) => o.display(context, a),
 			Expression::Tuple(_, v) => {
 				format!("({})",
 					v.iter()
-						.map(|x| x.display(context))
+						.map(|x| x.display_outer(context))
 						.collect::<Vec<String>>()
 						.join(", ")
 				)
@@ -81,6 +88,23 @@ impl Expression {
 		}
 	}
 
+	// All variables this expression refers to, in order.
+	pub fn get_variables(&self) -> Vec<(LineLocation, String)> {
+		if let Expression::Variable(l, s) = self {
+			return vec![(*l, s.clone())];
+		}
+
+		let Some(args) = self.get_args() else { return Vec::new() };
+		return args.iter().flat_map(|x| x.get_variables()).collect();
+	}
+
+	pub fn is_boolean(&self) -> bool {
+		match self {
+			Expression::Boolean(_,_) => true,
+			_ => false
+		}
+	}
+
 	// True if this is a unitless integer
 	pub fn is_unitless_integer(&self) -> bool {
 		match self {
@@ -172,6 +196,7 @@ impl Expression {
 			| Expression::Variable(l, _)
 			| Expression::Operator(l, _,_)
 			| Expression::Tuple(l, _)
+			| Expression::Boolean(l, _)
 			=> { *l }
 		}
 	}
@@ -183,6 +208,7 @@ impl Expression {
 			Expression::Variable(l, _) => { *l = *loc },
 			Expression::Operator(l, _,_) => { *l = *loc },
 			Expression::Tuple(l, _) => { *l = *loc },
+			Expression::Boolean(l, _) => { *l = *loc },
 		}
 	}
 }
\ No newline at end of file
@@ -37,7 +37,9 @@ pub enum Function {
 	FromCelsius,
 	ToCelsius,
 	FromFahrenheit,
-	ToFahrenheit
+	ToFahrenheit,
+
+	If
 }
 
 
@@ -74,59 +76,77 @@ impl ToString for Function {
 			Function::ToCelsius => {String::from("tocelsius") },
 			Function::FromFahrenheit => { String::from("fromfahrenheit") },
 			Function::ToFahrenheit => { String::from("tofahrenheit") },
+			Function::If => { String::from("if") },
 		}
 	}
 
 }
 
-impl Function {
-	#[inline(always)]
-	pub fn from_string(s: &str) -> Option<Function> {
-		match s {
-			"abs"     => {Some(Function::Abs)},
-			"floor"   => {Some(Function::Floor)},
-			"ceil"    => {Some(Function::Ceil)},
-			"round"   => {Some(Function::Round)},
-			"ln"      => {Some(Function::NaturalLog)},
-			"log"     => {Some(Function::TenLog)},
-			"sin"     => {Some(Function::Sin)},
-			"cos"     => {Some(Function::Cos)},
-			"tan"     => {Some(Function::Tan)},
-			"asin"    => {Some(Function::Asin)},
-			"acos"    => {Some(Function::Acos)},
-			"atan"    => {Some(Function::Atan)},
-			"csc"     => {Some(Function::Csc)},
-			"secant"  => {Some(Function::Sec)},
-			"cot"     => {Some(Function::Cot)},
-			"sinh"    => {Some(Function::Sinh)},
-			"cosh"    => {Some(Function::Cosh)},
-			"tanh"    => {Some(Function::Tanh)},
-			"asinh"   => {Some(Function::Asinh)},
-			"acosh"   => {Some(Function::Acosh)},
-			"atanh"   => {Some(Function::Atanh)},
-			"csch"    => {Some(Function::Csch)},
-			"sech"    => {Some(Function::Sech)},
-			"coth"    => {Some(Function::Coth)},
-
-			"nounit" => {Some(Function::NoUnit)},
-			"tobase" => {Some(Function::ToBase)},
-
-			"toC"            => {Some(Function::ToCelsius)},
-			"tocelsius"      => {Some(Function::ToCelsius)},
-			"toCelsius"      => {Some(Function::ToCelsius)},
-
-			"fromC"          => {Some(Function::FromCelsius)},
-			"fromcelsius"    => {Some(Function::FromCelsius)},
-			"fromCelsius"    => {Some(Function::FromCelsius)},
-
-			"toFahrenheit"   => {Some(Function::ToFahrenheit)},
-			"tofahrenheit"   => {Some(Function::ToFahrenheit)},
-			"toF"            => {Some(Function::ToFahrenheit)},
-
-			"fromF"          => {Some(Function::FromFahrenheit)},
-			"fromfahrenheit" => {Some(Function::FromFahrenheit)},
-			"fromFahrenheit" => {Some(Function::FromFahrenheit)},
-			_ => None
+// Generates `from_string` and `all_strings` from one list,
+// so completion always agrees with the parser.
+macro_rules! function_strings {
+	( $( $s:literal => $f:ident ),* $(,)? ) => {
+		impl Function {
+			#[inline(always)]
+			pub fn from_string(s: &str) -> Option<Function> {
+				match s {
+					$( $s => {Some(Function::$f)}, )*
+					_ => None
+				}
+			}
+
+			// Every string from_string accepts
+			pub fn all_strings() -> &'static [&'static str] {
+				return &[ $( $s ),* ];
+			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+function_strings!{
+	"abs"     => Abs,
+	"floor"   => Floor,
+	"ceil"    => Ceil,
+	"round"   => Round,
+	"ln"      => NaturalLog,
+	"log"     => TenLog,
+	"sin"     => Sin,
+	"cos"     => Cos,
+	"tan"     => Tan,
+	"asin"    => Asin,
+	"acos"    => Acos,
+	"atan"    => Atan,
+	"csc"     => Csc,
+	"secant"  => Sec,
+	"cot"     => Cot,
+	"sinh"    => Sinh,
+	"cosh"    => Cosh,
+	"tanh"    => Tanh,
+	"asinh"   => Asinh,
+	"acosh"   => Acosh,
+	"atanh"   => Atanh,
+	"csch"    => Csch,
+	"sech"    => Sech,
+	"coth"    => Coth,
+
+	"nounit" => NoUnit,
+	"tobase" => ToBase,
+
+	"toC"            => ToCelsius,
+	"tocelsius"      => ToCelsius,
+	"toCelsius"      => ToCelsius,
+
+	"fromC"          => FromCelsius,
+	"fromcelsius"    => FromCelsius,
+	"fromCelsius"    => FromCelsius,
+
+	"toFahrenheit"   => ToFahrenheit,
+	"tofahrenheit"   => ToFahrenheit,
+	"toF"            => ToFahrenheit,
+
+	"fromF"          => FromFahrenheit,
+	"fromfahrenheit" => FromFahrenheit,
+	"fromFahrenheit" => FromFahrenheit,
+
+	"if" => If,
+}
@@ -15,11 +15,24 @@ use super::Function;
 pub enum Operator {
 	// When adding operators, don't forget to update help command text.
 	// It isn't automatically generated.
-	ModuloLong = 0, // Mod invoked with "mod"
+	Or = 0,
+	And,
+	Not,
+
+	// Comparisons all have the same priority
+	Equal,
+	NotEqual,
+	Less,
+	Greater,
+	LessEqual,
+	GreaterEqual,
+
+	ModuloLong, // Mod invoked with "mod"
 	DivideLong, // Division invoked with "per"
 	UnitConvert,
 	Subtract,
 	Add,
+	PlusMinus, // A value and its uncertainty
 	Divide, // Division invoked with "/"
 	Multiply,
 	Modulo, // Mod invoked with %
@@ -32,7 +45,8 @@ pub enum Operator {
 	Factorial,
 
 	Function(Function),
-	UserFunction(String)
+	UserFunction(String),
+	NativeFunction(String)
 }
 
 impl PartialEq for Operator {
@@ -50,6 +64,9 @@ impl PartialOrd for Operator {
 			| (Operator::Divide, Operator::Multiply)
 			=> {Some(Ordering::Equal)}
 
+			(a, b) if a.is_comparison() && b.is_comparison()
+			=> {Some(Ordering::Equal)}
+
 			_ => { self.as_int().partial_cmp(&other.as_int()) }
 		}
 	}
@@ -73,9 +90,14 @@ impl Operator {
 			return Some(Operator::UserFunction(s.to_string()));
 		}
 
+		if context.is_native_function(s) {
+			return Some(Operator::NativeFunction(s.to_string()));
+		}
+
 		return match s {
 			"+"      => {Some( Operator::Add )},
 			"-"      => {Some( Operator::Subtract )},
+			"±"|"+/-" => {Some( Operator::PlusMinus )},
 			"neg"    => {Some( Operator::Negative )},
 			"*"|"×"  => {Some( Operator::Multiply )},
 			"/"|"÷"  => {Some( Operator::Divide )},
@@ -87,19 +109,44 @@ impl Operator {
 			"^"|"**" => {Some( Operator::Power )},
 			"!"      => {Some( Operator::Factorial )},
 			"sqrt"|"rt"|"√" => {Some( Operator::Sqrt )},
+			"=="     => {Some( Operator::Equal )},
+			"!="|"≠" => {Some( Operator::NotEqual )},
+			"<"      => {Some( Operator::Less )},
+			">"      => {Some( Operator::Greater )},
+			"<="|"≤" => {Some( Operator::LessEqual )},
+			">="|"≥" => {Some( Operator::GreaterEqual )},
+			"and"|"&&" => {Some( Operator::And )},
+			"or"|"||"  => {Some( Operator::Or )},
+			"not"      => {Some( Operator::Not )},
 
 			_ => None
 		};
 	}
 
+	#[inline(always)]
+	pub fn is_comparison(&self) -> bool {
+		match self {
+			Operator::Equal
+			| Operator::NotEqual
+			| Operator::Less
+			| Operator::Greater
+			| Operator::LessEqual
+			| Operator::GreaterEqual
+			=> true,
+			_ => false
+		}
+	}
+
 	#[inline(always)]
 	pub fn is_binary(&self) -> bool {
 		match self {
 			Operator::Negative
+			| Operator::Not
 			| Operator::Factorial
 			| Operator::Sqrt
 			| Operator::Function(_)
 			| Operator::UserFunction(_)
+			| Operator::NativeFunction(_)
 			=> false,
 			_ => true
 		}
@@ -109,9 +156,11 @@ impl Operator {
 	pub fn is_left_associative(&self) -> bool {
 		match self {
 			Operator::Negative
+			| Operator::Not
 			| Operator::Sqrt
 			| Operator::Function(_)
 			| Operator::UserFunction(_)
+			| Operator::NativeFunction(_)
 			=> false,
 			_ => true
 		}
@@ -157,12 +206,49 @@ impl Operator {
 			},
 
 			Operator::Sqrt => {
+				if !context.config.enable_unicode {
+					return format!("sqrt({})", args[0].display(context));
+				}
+
 				return format!(
 					"√{}",
 					self.add_parens_to_arg(context, &args[0]),
 				);
 			},
 
+			Operator::Not => {
+				return format!("not {}", self.add_parens_to_arg(context, &args[0]));
+			},
+
+			Operator::And
+			| Operator::Or
+			| Operator::Equal
+			| Operator::NotEqual
+			| Operator::Less
+			| Operator::Greater
+			| Operator::LessEqual
+			| Operator::GreaterEqual
+			=> {
+				let u = context.config.enable_unicode;
+				let o = match self {
+					Operator::And => "and",
+					Operator::Or => "or",
+					Operator::Equal => "==",
+					Operator::NotEqual => if u { "≠" } else { "!=" },
+					Operator::Less => "<",
+					Operator::Greater => ">",
+					Operator::LessEqual => if u { "≤" } else { "<=" },
+					Operator::GreaterEqual => if u { "≥" } else { ">=" },
+					_ => unreachable!()
+				};
+
+				return format!(
+					"{} {o} {}",
+					self.add_parens_to_arg(context, &args[0]),
+					self.add_parens_to_arg_strict(context, &args[1])
+				);
+			},
+
 			Operator::ModuloLong => {
 				return format!(
 					"{} mod {}",
@@ -203,6 +289,15 @@ impl Operator {
 				);
 			},
 
+			Operator::PlusMinus => {
+				let o = if context.config.enable_unicode { "±" } else { "+/-" };
+				return format!(
+					"{} {o} {}",
+					self.add_parens_to_arg(context, &args[0]),
+					self.add_parens_to_arg_strict(context, &args[1])
+				);
+			},
+
 			Operator::Power => {
 
 				let q = &args[1];
@@ -281,7 +376,7 @@ impl Operator {
 
 				if no_times {
 					if let Expression::Quantity(_, u) = b {
-						if u.unit.no_space() {
+						if u.unit.no_space(context) {
 							return format!("{}{}",
 								self.add_parens_to_arg_strict(context, a),
 								self.add_parens_to_arg_strict(context, b)
@@ -300,7 +395,8 @@ impl Operator {
 					};
 
 				} else {
-					return format!("{} × {}",
+					let o = if context.config.enable_unicode { "×" } else { "*" };
+					return format!("{} {o} {}",
 						self.add_parens_to_arg_strict(context, a),
 						self.add_parens_to_arg_strict(context, b)
 					);
@@ -313,24 +409,34 @@ impl Operator {
 
 
 				if let Expression::Quantity(_, q) = a {
-					if q.is_one() && context.config.enable_one_over_power {
+					if q.is_one() && q.unitless() && context.config.enable_one_over_power {
 						return format!("{}⁻¹",
 							self.add_parens_to_arg_strict(context, b)
 						);
 					}
 				}
 
-				return format!("{} ÷ {}",
+				let o = if context.config.enable_unicode { "÷" } else { "/" };
+				return format!("{} {o} {}",
 					self.add_parens_to_arg_strict(context, a),
 					self.add_parens_to_arg_strict(context, b)
 				);
 			},
 
-			Operator::Function(s) => {
-				return format!("{}({})", s.to_string(), args[0].display(context));
-			},
+			Operator::Function(_)
+			| Operator::UserFunction(_)
+			| Operator::NativeFunction(_) => {
+				let s = match self {
+					Operator::Function(f) => f.to_string(),
+					Operator::UserFunction(s) | Operator::NativeFunction(s) => s.clone(),
+					_ => unreachable!()
+				};
+
+				// Tuples already have parenthesis
+				if let Expression::Tuple(_, _) = args[0] {
+					return format!("{}{}", s, args[0].display(context));
+				}
 
-			Operator::UserFunction(s) => {
 				return format!("{}({})", s, args[0].display(context));
 			}
 		};
@@ -0,0 +1,117 @@
+use crate::context::Context;
+use crate::formattedtext::FormattedText;
+use crate::quantity::Unit;
+
+use super::stage;
+use super::{
+	Token,
+	Operator,
+	Constant
+};
+
+
+// Which tag should this word be drawn with?
+// `names` are being defined on this line, and shouldn't be
+// flagged as undefined even though they don't exist yet.
+fn word_tag(context: &Context, s: &str, names: &Vec<String>) -> &'static str {
+	if s == "true" || s == "false" { return "hconst"; }
+	if Constant::from_string(s).is_some() { return "hconst"; }
+	if Unit::from_string(s).is_some() { return "hunit"; }
+	if context.is_varible(s) || names.iter().any(|x| x == s) { return "hvar"; }
+	return "herr";
+}
+
+fn operator_tag(context: &Context, s: &str) -> &'static str {
+	return match Operator::from_string(context, s) {
+		None => "herr",
+		Some(Operator::Function(_)) => "hfunc",
+		Some(Operator::NativeFunction(_)) => "hfunc",
+		Some(Operator::UserFunction(_)) => "hufunc",
+		Some(_) => "hop"
+	};
+}
+
+
+// Wraps each token in `s` with highlighting tags.
+// `s` should already be substituted.
+pub fn highlight(context: &Context, s: &String) -> FormattedText {
+	let chars: Vec<char> = s.chars().collect();
+	let tokens: Vec<Token> = stage::tokenize(context, s).into();
+
+	// Tags for each token, None if the token should be drawn as-is.
+	let mut tags: Vec<Option<&str>> = vec![None; tokens.len()];
+
+	// Names on the left side of a definition.
+	// These may be undefined, and are visible on the right side.
+	let mut names: Vec<String> = Vec::new();
+	let mut function: Option<String> = None;
+	let mut depth = 0usize;
+	for (i, t) in tokens.iter().enumerate() {
+		match t {
+			Token::GroupStart(_) => { depth += 1; },
+			Token::GroupEnd(_) => { depth = depth.saturating_sub(1); },
+			Token::Operator(_, x) if x == "=" && depth == 0 => {
+				tags[i] = Some("hop");
+				for (j, t) in tokens[..i].iter().enumerate() {
+					let (Token::Word(_, x) | Token::Operator(_, x)) = t else { continue };
+					if j == 0 && i > 1 {
+						if let Token::GroupStart(_) = tokens[1] {
+							tags[j] = Some("hufunc");
+							function = Some(x.clone());
+							continue;
+						}
+					}
+					tags[j] = Some("hvar");
+					names.push(x.clone());
+				}
+				break;
+			},
+			_ => {}
+		}
+	}
+
+	// Find unmatched parenthesis
+	let mut open: Vec<usize> = Vec::new();
+	for (i, t) in tokens.iter().enumerate() {
+		match t {
+			Token::GroupStart(_) => { open.push(i); },
+			Token::GroupEnd(_) => {
+				if open.pop().is_none() { tags[i] = Some("herr"); }
+			},
+			_ => {}
+		}
+	}
+	for i in open { tags[i] = Some("herr"); }
+
+	let mut out = FormattedText::new(String::new());
+	let mut last = 0usize;
+	for (i, t) in tokens.iter().enumerate() {
+		let l = t.get_linelocation();
+
+		let tag = match tags[i] {
+			Some(x) => Some(x),
+			None => match t {
+				Token::Quantity(_, _) => Some("hnum"),
+				Token::Word(_, x) if function.as_ref() == Some(x) => Some("hufunc"),
+				Token::Word(_, x) => Some(word_tag(context, x, &names)),
+				Token::Operator(_, x) if names.contains(x) => Some("hvar"),
+				Token::Operator(_, x) => Some(operator_tag(context, x)),
+				_ => None
+			}
+		};
+
+		// Text between tokens (usually spaces)
+		out.push(&chars[last..l.pos].iter().collect::<String>());
+
+		let text: String = chars[l.pos..l.pos+l.len].iter().collect();
+		match tag {
+			Some(x) => { out.push(&format!("[{x}]{text}[n]")); },
+			None => { out.push(&text); }
+		}
+
+		last = l.pos + l.len;
+	}
+	out.push(&chars[last..].iter().collect::<String>());
+
+	return out;
+}
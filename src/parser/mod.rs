@@ -3,6 +3,8 @@ mod stage;
 mod token;
 mod expression;
 mod linelocation;
+mod statement;
+mod highlight;
 
 use self::token::Token;
 
@@ -12,8 +14,12 @@ pub use self::{
 	expression::Operator,
 	expression::Function,
 	linelocation::LineLocation,
+	statement::Statement,
+	highlight::highlight,
 };
 
+use std::collections::VecDeque;
+
 use crate::context::Context;
 use crate::errors::DaisyError;
 
@@ -31,12 +37,100 @@ pub fn parse(
 	return Ok(g);
 }
 
+// Like parse, but also accepts variable and function definitions.
+pub fn parse_statement(
+	context: &mut Context, s: &String
+) -> Result<Statement, (LineLocation, DaisyError)> {
+
+	let mut expressions = stage::tokenize(context, s);
+	if context.config.enable_substituion {
+		(_, expressions) = stage::find_subs(expressions);
+	}
+	let (d, expressions) = stage::find_definition(context, expressions)?;
+
+	return Ok(match d {
+		None => {
+			let g = stage::groupify(context, expressions)?;
+			let g = stage::treeify(context, g)?;
+			Statement::Expression(g)
+		},
+
+		Some(stage::Definition::Variable(l, s)) => {
+			let g = stage::groupify(context, expressions)?;
+			let g = stage::treeify(context, g)?;
+			Statement::VariableDefinition(l, s, g)
+		},
+
+		Some(stage::Definition::Function(l, s, a)) => {
+			let g = parse_function_tokens(context, &s, &a, expressions)?;
+			Statement::FunctionDefinition(l, s, a, g)
+		},
+	});
+}
+
+// Parse the body of function `name`.
+pub fn parse_function(
+	context: &mut Context, name: &String, args: &Vec<String>, s: &String
+) -> Result<Expression, (LineLocation, DaisyError)> {
+
+	let mut expressions = stage::tokenize(context, s);
+	if context.config.enable_substituion {
+		(_, expressions) = stage::find_subs(expressions);
+	}
+	let expressions = stage::function_body(name, args, expressions);
+
+	return parse_function_tokens(context, name, args, expressions);
+}
+
+fn parse_function_tokens(
+	context: &mut Context, name: &String, args: &Vec<String>, g: VecDeque<Token>
+) -> Result<Expression, (LineLocation, DaisyError)> {
+
+	let parse = |context: &mut Context| {
+		let g = stage::groupify(context, g)?;
+		return stage::treeify(context, g);
+	};
+
+	// Recursive functions call themselves in their body,
+	// so `name` must be a function while we parse it.
+	if context.is_function(name) { return parse(context); }
+	let stub = Expression::Tuple(LineLocation::new_zero(), VecDeque::new());
+	return context.with_function(name, args, &stub, parse);
+}
+
 pub fn parse_no_context(s: &String) -> Result<Expression, (LineLocation, DaisyError)> {
 	parse(&Context::new(), s)
 }
 
 
 
+// Should input `s` continue on the next line?
+// This is true if parenthesis are unbalanced,
+// or if `s` ends with a binary operator or `=`.
+pub fn needs_continuation(context: &Context, s: &String) -> bool {
+	let g = stage::tokenize(context, s);
+
+	let mut level: i64 = 0;
+	for t in &g {
+		match t {
+			Token::GroupStart(_) => { level += 1 },
+			Token::GroupEnd(_) => { level -= 1 },
+			_ => {}
+		}
+	}
+	if level > 0 { return true; }
+
+	return match g.back() {
+		Some(Token::Operator(_, o)) => {
+			o == "=" ||
+			Operator::from_string(context, o).is_some_and(|o| o.is_binary())
+		},
+		_ => false
+	};
+}
+
+
+
 // Substitiution replaces certain string with pretty unicode characters.
 // When it is enabled, ALL input strings are substituted. Variable and
 // operator tokens use the replaced string value. Make sure both the
@@ -94,10 +188,11 @@ pub fn substitute_cursor(
 			}
 		}
 
-		new_s.replace_range(
-			r.0.pos..r.0.pos+r.0.len,
-			&r.1[..]
-		)
+		// Locations count chars, but replace_range takes bytes.
+		// We work backwards, so everything before r is unchanged.
+		let byte = |i: usize| new_s.char_indices().nth(i).map_or(new_s.len(), |(b, _)| b);
+		let (a, b) = (byte(r.0.pos), byte(r.0.pos + r.0.len));
+		new_s.replace_range(a..b, &r.1[..])
 	}
 
 	return (new_c, new_s);
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+
+use crate::context::Context;
+use crate::errors::DaisyError;
+
+use super::super::{
+	Token,
+	LineLocation
+};
+
+
+/// The left side of a definition.
+#[derive(Debug)]
+pub enum Definition {
+	// Location of the name, name
+	Variable(LineLocation, String),
+
+	// Location of the whole header, name, arguments
+	Function(LineLocation, String, Vec<String>)
+}
+
+
+// Names on the left side of a definition may be words or,
+// if they're already defined as functions, operators.
+// Returns None if this token can't be a name.
+fn token_name(context: &Context, t: &Token) -> Option<(LineLocation, String)> {
+	match t {
+		Token::Word(l, s)
+		=> Some((*l, s.clone())),
+
+		Token::Operator(l, s)
+		if context.is_function(s)
+		=> Some((*l, s.clone())),
+
+		_ => None
+	}
+}
+
+
+// Parse the left side of a function definition.
+// `g` is everything after the function name.
+fn function_header(
+	context: &Context,
+	name_loc: LineLocation,
+	mut g: VecDeque<Token>
+) -> Result<(LineLocation, Vec<String>), (LineLocation, DaisyError)> {
+
+	let Some(Token::GroupStart(start)) = g.pop_front() else { unreachable!() };
+
+	let Some(end) = g.pop_back() else {
+		// `f(` with nothing after it
+		return Err((start, DaisyError::Syntax));
+	};
+	let Token::GroupEnd(end) = end else {
+		return Err((end.get_linelocation(), DaisyError::Syntax));
+	};
+
+	if g.len() == 0 {
+		return Err((start + end, DaisyError::EmptyGroup));
+	}
+
+	// Arguments must be names separated by commas
+	let mut args: Vec<String> = Vec::new();
+	let mut expect_name = true;
+	for t in g {
+		if expect_name {
+			let Some((l, s)) = token_name(context, &t) else {
+				return Err((t.get_linelocation(), DaisyError::Syntax));
+			};
+
+			if !context.valid_varible(&s) || args.contains(&s) {
+				return Err((l, DaisyError::BadVariable));
+			}
+
+			args.push(s);
+		} else {
+			let Token::TupleDelim(_) = t else {
+				return Err((t.get_linelocation(), DaisyError::Syntax));
+			};
+		}
+		expect_name = !expect_name;
+	}
+
+	// Trailing comma
+	if expect_name {
+		return Err((end, DaisyError::BadTuple));
+	}
+
+	return Ok((name_loc + end, args));
+}
+
+
+// Finds and removes the left side of a definition, if there is one.
+// Returns the definition header and the remaining tokens.
+//
+// This must be run before groupify, since groupify
+// inserts implicit operators that don't make sense here.
+pub fn find_definition(
+	context: &Context,
+	mut g: VecDeque<Token>
+) -> Result<
+	(Option<Definition>, VecDeque<Token>),
+	(LineLocation, DaisyError)
+> {
+
+	// Find the first `=` outside of parenthesis
+	let mut level = 0usize;
+	let mut eq: Option<usize> = None;
+	for (i, t) in g.iter().enumerate() {
+		match t {
+			Token::GroupStart(_) => { level += 1; },
+			Token::GroupEnd(_) => { level = level.saturating_sub(1); },
+			Token::Operator(l, s) if s == "=" => {
+				if level != 0 {
+					// Definitions can't be nested
+					return Err((*l, DaisyError::Syntax));
+				} else if eq.is_some() {
+					// A line can only contain one definition
+					return Err((*l, DaisyError::Syntax));
+				}
+				eq = Some(i);
+			},
+			_ => {}
+		}
+	}
+
+	let Some(eq) = eq else { return Ok((None, g)); };
+
+	let right = g.split_off(eq + 1);
+	let Some(Token::Operator(eq_loc, _)) = g.pop_back() else { unreachable!() };
+	let mut left = g;
+
+	if left.len() == 0 || right.len() == 0 {
+		return Err((eq_loc, DaisyError::Syntax));
+	}
+
+	let name_token = left.pop_front().unwrap();
+	let Some((name_loc, name)) = token_name(context, &name_token) else {
+		let l = name_token.get_linelocation();
+		if left.len() == 0 {
+			return Err((l, DaisyError::BadVariable));
+		} else {
+			return Err((l, DaisyError::BadFunction));
+		}
+	};
+
+	if left.len() == 0 {
+		if !context.valid_varible(&name) {
+			return Err((name_loc, DaisyError::BadVariable));
+		}
+
+		return Ok((Some(Definition::Variable(name_loc, name)), right));
+	}
+
+	// Anything other than `name(` can't be a valid left side.
+	let Some(Token::GroupStart(_)) = left.front() else {
+		let l = name_loc + left.back().unwrap().get_linelocation();
+		return Err((l, DaisyError::BadVariable));
+	};
+
+	if !context.valid_function(&name) {
+		return Err((name_loc, DaisyError::BadFunction));
+	}
+
+	let (l, args) = function_header(context, name_loc, left)?;
+	let right = function_body(&name, &args, right);
+
+	return Ok((Some(Definition::Function(l, name, args)), right));
+}
+
+
+// Prepare the tokens in the body of function `name`.
+pub fn function_body(
+	name: &String,
+	args: &Vec<String>,
+	g: VecDeque<Token>
+) -> VecDeque<Token> {
+	return g.into_iter().map(|t| {
+		match t {
+			// Argument names shadow user functions with the same name.
+			Token::Operator(l, s) if args.contains(&s) => Token::Word(l, s),
+
+			// Functions may call themselves,
+			// even if they aren't defined yet.
+			Token::Word(l, s) if &s == name && !args.contains(&s)
+			=> Token::Operator(l, s),
+
+			_ => t
+		}
+	}).collect();
+}
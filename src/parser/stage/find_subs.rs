@@ -15,6 +15,10 @@ fn sub_string(s: &str) -> Option<&'static str> {
 		"/"    => "÷",
 		"sqrt" => "√",
 		"rt"   => "√",
+		"<="   => "≤",
+		">="   => "≥",
+		"!="   => "≠",
+		"+/-"  => "±",
 
 
 
@@ -184,6 +184,19 @@ fn lookback(
 				=> {
 					return Err((*la + *lb, DaisyError::Syntax));
 				},
+
+				// Tuples may only follow functions,
+				// there is nothing to multiply them with.
+				(Token::Quantity(la,_), Token::Tuple(lb,_))
+				| (Token::Group(la,_), Token::Tuple(lb,_))
+				| (Token::Word(la,_), Token::Tuple(lb,_))
+				| (Token::Tuple(la,_), Token::Tuple(lb,_))
+				| (Token::Tuple(la,_), Token::Quantity(lb,_))
+				| (Token::Tuple(la,_), Token::Group(lb,_))
+				| (Token::Tuple(la,_), Token::Word(lb,_))
+				=> {
+					return Err((*la + *lb, DaisyError::Syntax));
+				},
 				_ => {g.insert(i-1, b); g.insert(i-1, a);}
 			}
 		}
@@ -291,7 +304,22 @@ pub fn groupify(
 	let q = is_tuple.pop().unwrap();
 
 	if q {
-		return Err((l, DaisyError::BadTuple));
+		// `to` may take a list of units without parentheses,
+		// like `5.3 ft to ft, in`. Everything after the last
+		// `to` before the first comma becomes a tuple.
+		let first = v.iter().position(|t| matches!(t, Token::TupleDelim(_))).unwrap();
+		let Some(i) = v.range(..first).rposition(
+			|t| matches!(t, Token::Operator(_, s) if s == "to")
+		) else {
+			return Err((l, DaisyError::BadTuple));
+		};
+
+		let mut rest = v.split_off(i + 1);
+		let mut lt = LineLocation::new_zero();
+		for t in &rest { lt += t.get_linelocation(); }
+
+		lookback(context, &mut rest)?;
+		v.push_back(Token::new_tuple(lt, rest)?);
 	}
 
 	lookback(context, &mut v)?;
@@ -1,11 +1,15 @@
 mod tokenize;
 mod find_subs;
+mod find_definition;
 mod groupify;
 mod treeify;
 
 pub (in super) use self::{
 	tokenize::tokenize,
 	find_subs::find_subs,
+	find_definition::find_definition,
+	find_definition::Definition,
+	find_definition::function_body,
 	groupify::groupify,
 	treeify::treeify,
 };
\ No newline at end of file
@@ -68,6 +68,17 @@ fn push_token(
 	g.push_back(t);
 }
 
+// Is this word the start of a numbered history reference,
+// like `ans12`, `$12` or `out[12`?
+fn is_history_prefix(s: &str) -> bool {
+	let Some(n) = s.strip_prefix("ans")
+		.or_else(|| s.strip_prefix("$"))
+		.or_else(|| s.strip_prefix("out["))
+		else { return false; };
+
+	return n.chars().all(|c| c.is_ascii_digit());
+}
+
 /// Turns a string into Tokens. First stage of parsing.
 pub fn tokenize(context: &Context, input: &String) -> VecDeque<Token> {
 	let mut t: Option<Token> = None; // The current token we're reading
@@ -85,6 +96,13 @@ pub fn tokenize(context: &Context, input: &String) -> VecDeque<Token> {
 						val.push(if c == ',' {'.'} else {c});
 					},
 
+					// Digits in history references
+					// are part of that word.
+					Some(Token::Word(_, val))
+					if c != '.' && is_history_prefix(val) => {
+						val.push(c);
+					},
+
 					// If we're not building a number, finalize
 					// previous token and start one.
 					_ => {
@@ -130,8 +148,15 @@ pub fn tokenize(context: &Context, input: &String) -> VecDeque<Token> {
 						}
 					},
 
+					// `+/-` is the same as `±`.
+					// `+-` isn't, `1+-2` is `1 + (-2)`.
+					Some(Token::Operator(l, val))
+					if c == '-' && val == "+/" && l.pos + 2 == i => {
+						val.push(c);
+					},
+
 					// This may be a negative or a subtraction.
-					// Multi-character operators with - and + are NOT supported!
+					// Other multi-character operators with - and + are NOT supported!
 					// (for example, we can't use -> for unit conversion)
 					_ => {
 						push_token(context, &mut g, t, i);
@@ -148,11 +173,17 @@ pub fn tokenize(context: &Context, input: &String) -> VecDeque<Token> {
 				t = Some(Token::TupleDelim(LineLocation{pos: i, len: 1}));
 			},
 
+			'±' => {
+				push_token(context, &mut g, t, i);
+				t = Some(Token::Operator(LineLocation{pos: i, len: 1}, String::from(c)));
+			},
+
 			// Operator
 			'^'|'!'|'%'|'\\'|
 			'*'|'×'|'/'|'÷'|
 			'>'|'<'|'?'|'@'|
-			'&'|'|'|'~'
+			'&'|'|'|'~'|'='|
+			'≤'|'≥'|'≠'
 			=> {
 				match &mut t {
 					Some(Token::Operator(_, val)) => { val.push(c); },
@@ -174,7 +205,8 @@ pub fn tokenize(context: &Context, input: &String) -> VecDeque<Token> {
 			},
 
 			// Space. Basic seperator.
-			' ' => {
+			// Newlines separate lines of multi-line input.
+			' ' | '\n' => {
 				push_token(context, &mut g, t, i);
 				t = None;
 			}
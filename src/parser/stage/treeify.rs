@@ -148,6 +148,13 @@ fn treeify_binary(
 			(l, o.unwrap())
 		};
 
+		// Outside of function arguments, tuples may only be a list
+		// of units on the right of `to`, like `5.3 ft to ft, in`.
+		if let Expression::Tuple(lt, _) = &left { return Err((*lt, DaisyError::BadTuple)); }
+		if let Expression::Tuple(lt, _) = &right {
+			if o != Operator::UnitConvert { return Err((*lt, DaisyError::BadTuple)); }
+		}
+
 		let mut new_token_args: VecDeque<Expression> = VecDeque::with_capacity(2);
 		new_token_args.push_back(left);
 		new_token_args.push_back(right);
@@ -272,6 +279,13 @@ fn treeify_unary(
 				(l, o.unwrap())
 			};
 
+			// Only functions take tuples
+			if let Expression::Tuple(lt, _) = &next {
+				if !matches!(o, Operator::Function(_) | Operator::UserFunction(_) | Operator::NativeFunction(_)) {
+					return Err((*lt, DaisyError::BadTuple));
+				}
+			}
+
 			let mut new_token_args: VecDeque<Expression> = VecDeque::with_capacity(3);
 			new_token_args.push_back(next);
 
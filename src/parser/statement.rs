@@ -0,0 +1,18 @@
+use super::Expression;
+use super::LineLocation;
+
+
+/// A complete line of input.
+#[derive(Debug)]
+pub enum Statement {
+	// A plain expression, like `1 + 1`
+	Expression(Expression),
+
+	// A variable definition, like `x = 1 + 1`
+	// Location of the name, name, value
+	VariableDefinition(LineLocation, String, Expression),
+
+	// A function definition, like `f(x) = x + 1`
+	// Location of the header, name, arguments, body
+	FunctionDefinition(LineLocation, String, Vec<String>, Expression)
+}
@@ -84,6 +84,12 @@ impl Token {
 			},
 
 			Token::Word(l, s) => {
+				match &s[..] {
+					"true" => { return Ok(Expression::Boolean(l, true)); },
+					"false" => { return Ok(Expression::Boolean(l, false)); },
+					_ => {}
+				}
+
 				let c = Constant::from_string(&s);
 				if c.is_some() { return Ok(Expression::Constant(l, c.unwrap())); }
 
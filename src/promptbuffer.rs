@@ -1,10 +1,31 @@
 use std::collections::VecDeque;
 use crate::FormattedText;
 use crate::parser::substitute_cursor;
+use crate::parser::Statement;
+use crate::parser::Expression;
+use crate::parser::Function;
+use crate::parser::Constant;
+use crate::parser;
+use crate::quantity::all_unit_strings;
+use crate::evaluate;
+use crate::command;
 use crate::Context;
+use crate::errors::DaisyError;
+
+use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
 
 const PROMPT_STR: &str = "==> ";
 
+// Shown while continuing multi-line input.
+// Must be as long as PROMPT_STR.
+const CONTINUE_STR: &str = "... ";
+
+// The preview runs on every keystroke, so it gives up
+// on long input and deep function calls.
+const PREVIEW_MAX_LEN: usize = 200;
+const PREVIEW_MAX_DEPTH: usize = 32;
+
 #[derive(Debug)]
 pub struct PromptBuffer {
 	// History
@@ -19,20 +40,127 @@ pub struct PromptBuffer {
 	pub buffer: String,
 	buffer_changed: bool,
 	cursor: usize,
-	last_print_len: usize
+	last_print_len: usize,
+
+	// Length of the preview drawn below the prompt
+	last_preview_len: usize,
+
+	// Tab completion state.
+	// Start of the word being completed, candidates, and
+	// the index of the candidate we're showing.
+	// None if the last key wasn't tab.
+	completion: Option<(usize, Vec<String>, usize)>,
+
+	// Reverse history search state.
+	// Query, index of the current match in hist
+	// (hist.len() if there is none), and the buffer
+	// we had before searching.
+	// None if we aren't searching.
+	search: Option<(String, usize, String)>,
+
+	// Killed text, for yanking
+	yank: String,
+
+	// Finished lines of multi-line input.
+	// Empty if we aren't continuing a line.
+	lines: Vec<String>
+}
+
+// Combining characters are drawn on top of the character before them.
+// The cursor should never sit between the two.
+fn is_combining(c: char) -> bool {
+	return c.width() == Some(0);
+}
+
+// Where the cursor goes when moving left from char i.
+fn prev_boundary(chars: &[char], mut i: usize) -> usize {
+	if i == 0 { return 0; }
+	i -= 1;
+	while i > 0 && is_combining(chars[i]) { i -= 1; }
+	return i;
+}
+
+// Where the cursor goes when moving right from char i.
+fn next_boundary(chars: &[char], mut i: usize) -> usize {
+	if i == chars.len() { return i; }
+	i += 1;
+	while i < chars.len() && is_combining(chars[i]) { i += 1; }
+	return i;
+}
+
+// Which characters are part of a word?
+// Used for completion and word-wise movement.
+fn is_word_char(c: char) -> bool {
+	return c.is_alphanumeric() || c == '_';
 }
 
 impl PromptBuffer {
 	// Same as write_primpt, but pretends there is no cursor
+	// and clears the preview. Used when a line is finished.
 	pub fn write_prompt_nocursor(&mut self, context: &Context) -> FormattedText {
+		self.end_search();
 		let tmp = self.cursor;
 		self.cursor = 0;
-		let r = self.write_prompt(context);
+		let r = self.draw_prompt(context, false);
 		self.cursor = tmp;
 		return r;
 	}
 
 	pub fn write_prompt(&mut self, context: &Context) -> FormattedText {
+		return self.draw_prompt(context, true);
+	}
+
+	// Evaluate the buffer and describe the result in one line.
+	// This works on a copy of `context`, so nothing we do here
+	// (defining variables, saving history) is visible outside.
+	pub fn preview(&self, context: &Context) -> Option<String> {
+		let s = &self.get_input();
+		if s.trim().len() == 0 { return None; }
+		if s.chars().count() > PREVIEW_MAX_LEN { return None; }
+		if s.trim() == "quit" || command::is_command(s) { return None; }
+
+		// History is shared with `context`, so this copy is cheap.
+		let mut context = context.clone();
+		context.config.enable_suggestions = false;
+		context.config.enable_native_functions = false;
+		context.config.max_call_depth = context.config.max_call_depth.min(PREVIEW_MAX_DEPTH);
+
+		// Is this a finished value?
+		// Native functions aren't called here, so results that need one aren't.
+		let done = |x: &Expression| match x {
+			Expression::Tuple(_, v) => v.iter().all(|x| x.is_quantity() || x.is_boolean()),
+			_ => x.is_quantity() || x.is_boolean()
+		};
+
+		let r = match parser::parse_statement(&mut context, s) {
+			Err(e) => Err(e),
+
+			Ok(Statement::Expression(g)) => {
+				let r = evaluate::evaluate(&mut context, &g);
+				if r.as_ref().is_ok_and(|x| !done(x)) { return None; }
+				r.map(|x| format!("= {}", x.display_outer(&context)))
+			},
+
+			Ok(Statement::VariableDefinition(_, name, g)) => {
+				evaluate::evaluate_variable(&mut context, &name, &g)
+					.map(|x| format!("{name} = {}", x.display_outer(&context)))
+			},
+
+			Ok(Statement::FunctionDefinition(_, name, args, g)) => {
+				evaluate::check_function(&mut context, &name, &args, &g)
+					.map(|_| format!("{name}({}) = {}", args.join(", "), g.display(&context)))
+			}
+		};
+
+		return match r {
+			Ok(t) => Some(t),
+			// This might work with the full call depth
+			Err((_, DaisyError::TooDeep)) => None,
+			Err((_, e)) => Some(e.text().plain())
+		};
+	}
+
+	fn draw_prompt(&mut self, context: &Context, show_preview: bool) -> FormattedText {
 		let l = self.buffer.chars().count();
 		let i = if l == 0 {0} else {l - self.cursor};
 
@@ -41,18 +169,62 @@ impl PromptBuffer {
 	
 		let mut tx = FormattedText::new("".to_string());
 
-		tx.push(&format!("\r[p]{PROMPT_STR}[n]{s}"));
+		// Where to put the cursor, in columns
+		let q: usize;
+
+		// Total width of this line, in columns
+		let line_len: usize;
+
+		if let Some((query, _, _)) = &self.search {
+			let failed = query.len() != 0 && !self.buffer.contains(query.as_str());
+			let p = format!(
+				"({}reverse-i-search)`{query}': ",
+				if failed { "failed " } else { "" }
+			);
+
+			// The cursor sits at the end of the query
+			q = p.width() - 3;
+			line_len = p.width() + s.width();
+			tx.push(&format!("\r[p]{p}[n]"));
+		} else {
+			let p = if self.lines.len() == 0 { PROMPT_STR } else { CONTINUE_STR };
+			// Wide characters take two columns, combining characters none.
+			q = p.width() + s.chars().take(display_c).map(|c| c.width().unwrap_or(0)).sum::<usize>();
+			line_len = p.width() + s.width();
+			tx.push(&format!("\r[p]{p}[n]"));
+		}
+
+		if self.lines.len() == 0 {
+			tx += parser::highlight(context, &s);
+		} else {
+			// Highlight this line along with the ones before it,
+			// so we know about their parenthesis and definitions.
+			let h = parser::highlight(context, &format!("{}\n{s}", self.lines.join("\n")));
+			tx += h.last_line();
+		}
 
 
 		// If this string is shorter, clear the remaining old one.
-		if s.chars().count() < self.last_print_len {
-			tx.push(&" ".repeat(self.last_print_len - s.chars().count()));
+		if line_len < self.last_print_len {
+			tx.push(&" ".repeat(self.last_print_len - line_len));
 		}
 
-		let q = (display_c + PROMPT_STR.chars().count()) as u16;
-		tx.push(&format!("\r[cursorright{q}]"));
+		self.last_print_len = line_len;
+
+		// Draw the preview on the next line,
+		// clearing whatever was there before.
+		let p = if show_preview { self.preview(context) } else { None };
+		let p = p.unwrap_or(String::new());
+		if p.len() != 0 || self.last_preview_len != 0 {
+			let l = p.width();
+			tx.push(&format!(
+				"\n[d]{p}[n]{}[cursorup1]",
+				" ".repeat(self.last_preview_len.saturating_sub(l))
+			));
+			self.last_preview_len = l;
+		}
 
-		self.last_print_len = s.chars().count();
+		tx.push(&format!("\r[cursorright{q}]"));
 
 		return tx;
 	}
@@ -69,22 +241,55 @@ impl PromptBuffer {
 			buffer_changed: false,
 			cursor: 0,
 			last_print_len: 0,
+			last_preview_len: 0,
+			completion: None,
+			search: None,
+			yank: String::new(),
+			lines: Vec::new(),
 		};
 	}
 
 	// Prompt methods
 	pub fn get_contents(&self) -> &String {&self.buffer}
 
-	pub fn enter(&mut self) -> String {
+	// Called by every key that isn't tab or part of a search.
+	fn interrupt(&mut self) {
+		self.completion = None;
+		self.end_search();
+	}
+
+	// Everything typed so far, including earlier lines of
+	// multi-line input. Lines are separated by newlines.
+	pub fn get_input(&self) -> String {
+		if self.lines.len() == 0 { return self.buffer.clone(); }
+		return format!("{}\n{}", self.lines.join("\n"), self.buffer);
+	}
+
+	// Finish this line. Returns the input we should run,
+	// or an empty string if the input continues on the next line.
+	// An empty line ends multi-line input, even if it's incomplete.
+	pub fn enter(&mut self, context: &Context) -> String {
+		self.interrupt();
 		// Don't trim input string so that linelocations are correct
 		//let s = String::from(self.buffer.trim());
-		let s = self.buffer.clone();
+		let line = self.buffer.clone();
 		self.buffer.clear();
 		self.hist_cursor = 0;
 		self.cursor = 0;
 		self.buffer_changed = false;
 
-		if s != "" { self.hist.push_back(s.clone()); }
+		if line.trim() != "" || self.lines.len() == 0 {
+			self.lines.push(line.clone());
+		}
+
+		let s = self.lines.join("\n");
+		if line.trim() != "" && parser::needs_continuation(context, &s) {
+			return String::new();
+		}
+		self.lines.clear();
+
+		// Multi-line input is saved as one line
+		if s != "" { self.hist.push_back(s.replace('\n', " ")); }
 		while self.hist.len() > self.hist_maxlen {
 			self.hist.pop_front();
 		}
@@ -94,57 +299,300 @@ impl PromptBuffer {
 
 	// Buffer manipulation
 	pub fn add_char(&mut self, c: char) {
-		self.buffer_changed = true;
+		self.completion = None;
 
-		if self.cursor == 0 {
-			self.buffer.push(c);
-		} else {
-			let l = self.buffer.chars().count();
-			let i = l - self.cursor;
-			self.buffer.insert(i, c);
+		if let Some((query, m, _)) = &mut self.search {
+			query.push(c);
+
+			// The current match may still match
+			let at = (*m + 1).min(self.hist.len());
+			self.search_from(at);
+			return;
 		}
+
+		self.buffer_changed = true;
+
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		chars.insert(i, c);
+		self.buffer = chars.into_iter().collect();
 	}
-	pub fn backspace(&mut self) {
-		if self.buffer.len() == 0 { return }
+
+	// Insert pasted text at the cursor.
+	// Pasted text is never treated as a key press, so newlines
+	// and tabs become spaces and other control characters are dropped.
+	pub fn paste(&mut self, s: &str) {
+		self.interrupt();
 		self.buffer_changed = true;
-		let l = self.buffer.chars().count();
 
-		if self.cursor == 0 {
-			self.buffer.pop();
-		} else if self.cursor != l {
-			let i = l - self.cursor;
-			self.buffer.remove(i-1);
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		chars.splice(i..i, s.chars().filter(|c| *c != '\r').filter_map(|c| {
+			if c == '\n' || c == '\t' { Some(' ') }
+			else if c.is_control() { None }
+			else { Some(c) }
+		}));
+		self.buffer = chars.into_iter().collect();
+	}
 
-			if self.cursor >= l {
-				self.cursor = l-1;
-			}
+	pub fn backspace(&mut self) {
+		self.completion = None;
+
+		if let Some((query, _, _)) = &mut self.search {
+			query.pop();
+			self.search_from(self.hist.len());
+			return;
 		}
+
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		if i == 0 { return; }
+		self.buffer_changed = true;
+
+		// Remove combining characters with their base
+		chars.drain(prev_boundary(&chars, i)..i);
+		self.buffer = chars.into_iter().collect();
 	}
 
 	pub fn delete(&mut self) {
-		if self.cursor != 0 {
-			self.cursor -= 1;
-			self.backspace();
-		}
+		self.interrupt();
+
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let l = chars.len();
+		let i = l - self.cursor;
+		if i == l { return; }
+		self.buffer_changed = true;
+
+		let j = next_boundary(&chars, i);
+		chars.drain(i..j);
+		self.buffer = chars.into_iter().collect();
+		self.cursor = l - j;
 	}
 
 
 	// Cursor manipulation
 	pub fn cursor_left(&mut self) {
+		self.interrupt();
+		let chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		self.cursor = chars.len() - prev_boundary(&chars, i);
+	}
+
+	pub fn cursor_right(&mut self) {
+		self.interrupt();
+		let chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		self.cursor = chars.len() - next_boundary(&chars, i);
+	}
+
+	pub fn cursor_home(&mut self) {
+		self.interrupt();
+		self.cursor = self.buffer.chars().count();
+	}
+
+	pub fn cursor_end(&mut self) {
+		self.interrupt();
+		self.cursor = 0;
+	}
+
+	// Move to the start of this word, or the previous one.
+	pub fn word_left(&mut self) {
+		self.interrupt();
+		let chars: Vec<char> = self.buffer.chars().collect();
+		let mut i = chars.len() - self.cursor;
+
+		while i > 0 && !is_word_char(chars[i-1]) { i -= 1; }
+		while i > 0 && is_word_char(chars[i-1]) { i -= 1; }
+
+		self.cursor = chars.len() - i;
+	}
+
+	// Move to the end of this word, or the next one.
+	pub fn word_right(&mut self) {
+		self.interrupt();
+		let chars: Vec<char> = self.buffer.chars().collect();
+		let mut i = chars.len() - self.cursor;
+
+		while i < chars.len() && !is_word_char(chars[i]) { i += 1; }
+		while i < chars.len() && is_word_char(chars[i]) { i += 1; }
+
+		self.cursor = chars.len() - i;
+	}
+
+
+	// Kill and yank
+	// Remove chars a..b from the buffer, saving them for yank.
+	fn kill(&mut self, a: usize, b: usize) {
+		if a == b { return; }
+
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let l = chars.len();
+		self.yank = chars.drain(a..b).collect();
+		self.buffer = chars.into_iter().collect();
+		self.buffer_changed = true;
+
+		// Everything after b stays after the cursor
+		self.cursor = l - b;
+	}
+
+	// Kill the space-separated word before the cursor
+	pub fn kill_word(&mut self) {
+		self.interrupt();
+		let chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		let mut j = i;
+
+		while j > 0 && chars[j-1] == ' ' { j -= 1; }
+		while j > 0 && chars[j-1] != ' ' { j -= 1; }
+
+		self.kill(j, i);
+	}
+
+	// Kill everything before the cursor
+	pub fn kill_start(&mut self) {
+		self.interrupt();
+		let l = self.buffer.chars().count();
+		self.kill(0, l - self.cursor);
+	}
+
+	// Kill everything after the cursor
+	pub fn kill_end(&mut self) {
+		self.interrupt();
 		let l = self.buffer.chars().count();
-		if self.cursor < l {
-			self.cursor += 1;
+		self.kill(l - self.cursor, l);
+	}
+
+	// Insert the last killed text
+	pub fn yank(&mut self) {
+		self.interrupt();
+		if self.yank.len() == 0 { return; }
+
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+		chars.splice(i..i, self.yank.chars());
+		self.buffer = chars.into_iter().collect();
+		self.buffer_changed = true;
+	}
+
+
+	// Reverse incremental history search.
+	// Starts a search, or finds an older match if we're already searching.
+	pub fn search(&mut self) {
+		self.completion = None;
+
+		match &self.search {
+			None => {
+				self.search = Some((String::new(), self.hist.len(), self.buffer.clone()));
+			},
+			Some((_, m, _)) => {
+				let m = *m;
+				self.search_from(m);
+			}
 		}
 	}
 
-	pub fn cursor_right(&mut self) {
-		if self.cursor > 0 {
-			self.cursor -= 1;
+	// Stop searching, and go back to what we had before.
+	pub fn cancel_search(&mut self) {
+		let Some((_, _, original)) = self.search.take() else { return };
+		self.buffer = original;
+		self.cursor = 0;
+	}
+
+	// Stop searching and keep the current match.
+	// History navigation continues from the match.
+	fn end_search(&mut self) {
+		let Some((_, m, _)) = self.search.take() else { return };
+		if m < self.hist.len() {
+			self.hist_cursor = self.hist.len() - m;
+			self.buffer_changed = false;
+		}
+	}
+
+	// Find the newest match for the query that is older than hist[at].
+	fn search_from(&mut self, at: usize) {
+		let Some((query, m, _)) = &mut self.search else { return };
+		if query.len() == 0 { return; }
+
+		for j in (0..at).rev() {
+			if self.hist[j].contains(query.as_str()) {
+				*m = j;
+				self.buffer = self.hist[j].clone();
+				self.cursor = 0;
+				return;
+			}
+		}
+	}
+
+
+	// Completion
+	fn candidates(context: &Context, prefix: &str, first_word: bool) -> Vec<String> {
+		let mut v: Vec<String> = Vec::new();
+
+		v.extend(Function::all_strings().iter().map(|x| x.to_string()));
+		v.extend(all_unit_strings().iter().map(|x| x.to_string()));
+		for c in Constant::all_consts() {
+			v.extend(c.source_strings().iter().map(|x| x.to_string()));
+		}
+		v.extend(context.get_variables().keys().cloned());
+		v.extend(context.get_functions().keys().cloned());
+		v.extend(context.get_native_functions().keys().cloned());
+
+		// Commands are only valid at the start of a line
+		if first_word {
+			v.extend(command::all_commands().iter().map(|x| x.to_string()));
+		}
+
+		// Don't suggest names we can't print
+		v.retain(|x| x.starts_with(prefix) && (context.config.enable_unicode || x.is_ascii()));
+		v.sort_by(|a, b| (a.chars().count(), a).cmp(&(b.chars().count(), b)));
+		v.dedup();
+		return v;
+	}
+
+	// Complete the word before the cursor.
+	// Pressing tab again cycles through candidates.
+	pub fn complete(&mut self, context: &Context) {
+		self.end_search();
+		let mut chars: Vec<char> = self.buffer.chars().collect();
+		let i = chars.len() - self.cursor;
+
+		let (start, candidates, n) = match self.completion.take() {
+			Some((start, candidates, n)) => {
+				let n = (n + 1) % candidates.len();
+				(start, candidates, n)
+			},
+
+			None => {
+				let mut start = i;
+				while start > 0 && is_word_char(chars[start-1]) {
+					start -= 1;
+				}
+				if start == i { return; }
+
+				let prefix: String = chars[start..i].iter().collect();
+				let first_word = chars[..start].iter().all(|c| *c == ' ');
+				let candidates = PromptBuffer::candidates(context, &prefix, first_word);
+				if candidates.len() == 0 { return; }
+
+				// Don't "complete" a word that is already complete,
+				// show the next candidate instead.
+				let n = if candidates.len() > 1 && candidates[0] == prefix { 1 } else { 0 };
+				(start, candidates, n)
+			}
+		};
+
+		chars.splice(start..i, candidates[n].chars());
+		self.buffer = chars.into_iter().collect();
+		self.buffer_changed = true;
+
+		if candidates.len() > 1 {
+			self.completion = Some((start, candidates, n));
 		}
 	}
 
 	// History manipulation
 	pub fn hist_up(&mut self) {
+		self.interrupt();
 		if self.buffer_changed && self.buffer.len() != 0 { return; }
 
 		if self.hist_cursor < self.hist.len() {
@@ -161,6 +609,7 @@ impl PromptBuffer {
 		}
 	}
 	pub fn hist_down(&mut self) {
+		self.interrupt();
 		if self.buffer_changed && self.buffer.len() != 0 { return; }
 
 		if self.hist_cursor > 0 {
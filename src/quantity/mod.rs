@@ -24,6 +24,8 @@ pub use crate::quantity::unit::Unit;
 pub use crate::quantity::unit::FreeUnit;
 pub use crate::quantity::unit::WholeUnit;
 pub use crate::quantity::unit::freeunit_from_string;
+pub use crate::quantity::unit::all_unit_strings;
+pub use crate::quantity::unit::Rates;
 
 mod quantity;
 pub use crate::quantity::quantity::Quantity;
@@ -10,27 +10,120 @@ use std::cmp::Ordering;
 use crate::context::Context;
 use crate::quantity::Unit;
 use crate::quantity::FreeUnit;
+use crate::quantity::WholeUnit;
+use crate::quantity::Rates;
 
 use crate::quantity::Scalar;
 
+/// A number with a unit attached to it.
+///
+/// `scalar` is the numeric value in terms of `unit`,
+/// so `3 km` has a scalar of 3 and a unit of `km`.
+/// Both fields are public, but the accessors below are
+/// preferred when embedding daisy.
 #[derive(Debug)]
 #[derive(Clone)]
 pub struct Quantity {
+	/// The numeric part of this quantity.
+	/// This is exact (rational) whenever possible.
 	pub scalar: Scalar,
-	pub unit: Unit
+
+	/// The unit of this quantity. Empty if this is a plain number.
+	pub unit: Unit,
+
+	/// The standard uncertainty of this quantity, in terms of `unit`.
+	/// `None` if this quantity is exact, like most numbers.
+	pub uncertainty: Option<Scalar>
+}
+
+
+// 0 and 1, for derivatives below
+fn zero() -> Scalar { Scalar::new_rational(0f64).unwrap() }
+fn one() -> Scalar { Scalar::new_rational(1f64).unwrap() }
+
+// 1/x, for derivatives below.
+// Rationals can't divide by zero, so a zero gives an infinite slope.
+fn inv(x: Scalar) -> Scalar {
+	if x.is_zero() { return Scalar::new_float(f64::INFINITY).unwrap(); }
+	return one() / x;
+}
+
+// 1/√x, for derivatives below
+fn inv_sqrt(x: Scalar) -> Scalar { x.pow(Scalar::new_rational(-0.5f64).unwrap()) }
+
+// Scale uncertainty `s` by |k|, where k is the derivative of some function.
+// `k` is only computed if there is an uncertainty.
+// A zero derivative gives an exact result.
+fn scale(s: &Option<Scalar>, k: impl FnOnce() -> Scalar) -> Option<Scalar> {
+	let k = k();
+	if k.is_zero() { return None; }
+	return s.as_ref().map(|s| s.clone() * k.abs());
+}
+
+// Combine independent uncertainties, √(a² + b²).
+// Exact values add no uncertainty.
+fn combine(a: Option<Scalar>, b: Option<Scalar>) -> Option<Scalar> {
+	return match (a, b) {
+		(None, x) | (x, None) => x,
+		(Some(a), Some(b)) => Scalar::new_float(a.to_f64().hypot(b.to_f64()))
+	};
+}
+
+// Write `v ± u` so both have the same number of decimals.
+// `u` keeps one significant digit, or two if its first digit is 1.
+fn uncertain_string(v: f64, u: f64, pm: &str) -> String {
+	if !u.is_finite() || u <= 0f64 || !v.is_finite() {
+		return format!("{v} {pm} {u}");
+	}
+
+	// Power of ten of u's last digit
+	let e = u.log10().floor() as i32;
+	let mut d = e;
+	if u / 10f64.powi(e) < 1.95 { d -= 1; }
+
+	// Use scientific notation for very big and very small numbers,
+	// like dec_to_sci does.
+	let p = v.abs().max(u).log10().floor() as i32;
+	let (p, d) = if p.abs() >= 5 { (p, d - p) } else { (0, d) };
+	let (v, u) = (v / 10f64.powi(p), u / 10f64.powi(p));
+
+	// Round to the last digit of u
+	let k = 10f64.powi(d);
+	let (mut v, u) = ((v / k).round() * k, (u / k).round() * k);
+	if v == 0f64 { v = 0f64; } // Don't print -0
+
+	let n = (-d).max(0) as usize;
+	if p == 0 {
+		return format!("{v:.n$} {pm} {u:.n$}");
+	} else {
+		return format!("({v:.n$} {pm} {u:.n$})e{p}");
+	}
 }
 
 
 
 impl Quantity {
+	// The number part of this quantity, with its uncertainty.
+	fn display_number(&self, context: &Context) -> String {
+		let Some(u) = &self.uncertainty else { return self.scalar.to_string() };
+		let pm = if context.config.enable_unicode { "±" } else { "+/-" };
+		return uncertain_string(self.scalar.to_f64(), u.to_f64(), pm);
+	}
+
+	/// Format this quantity for printing inside a larger expression.
 	pub fn display(&self, context: &Context) -> String {
+		// `±` binds loosely, so keep it together
+		if self.uncertainty.is_some() {
+			return format!("({})", self.display_outer(context));
+		}
+
 		let n = self.scalar.to_string();
 		if self.unitless() { return n; }
 
 		let u = self.unit.display(context);
 		if self.is_one() { return u; };
 
-		if self.unit.no_space() {
+		if self.unit.no_space(context) {
 			return format!("{n}{u}");
 		} else {
 			return format!("{n} {u}");
@@ -39,12 +132,13 @@ impl Quantity {
 }
 
 impl Quantity {
+	/// Format this quantity for printing on its own.
 	pub fn display_outer(&self, context: &Context) -> String {
-		let n = self.scalar.to_string();
+		let n = self.display_number(context);
 		if self.unitless() { return n; }
 
 		let u = self.unit.display(context);
-		if self.unit.no_space() {
+		if self.unit.no_space(context) {
 			return format!("{n}{u}");
 		} else {
 			return format!("{n} {u}");
@@ -57,7 +151,8 @@ impl Quantity {
 
 		return Some(Quantity{
 			scalar: v.unwrap(),
-			unit: Unit::new()
+			unit: Unit::new(),
+			uncertainty: None
 		});
 	}
 
@@ -67,7 +162,8 @@ impl Quantity {
 
 		return Some(Quantity{
 			scalar: v.unwrap(),
-			unit: Unit::new()
+			unit: Unit::new(),
+			uncertainty: None
 		});
 	}
 
@@ -77,7 +173,8 @@ impl Quantity {
 
 		return Some(Quantity{
 			scalar: v.unwrap(),
-			unit: Unit::new()
+			unit: Unit::new(),
+			uncertainty: None
 		});
 	}
 
@@ -87,7 +184,8 @@ impl Quantity {
 
 		return Some(Quantity{
 			scalar: v.unwrap(),
-			unit: Unit::new()
+			unit: Unit::new(),
+			uncertainty: None
 		});
 	}
 
@@ -97,32 +195,73 @@ impl Quantity {
 
 		return Some(Quantity{
 			scalar: v.unwrap(),
-			unit: Unit::new()
+			unit: Unit::new(),
+			uncertainty: None
 		});
 	}
 
 	pub fn from_scalar(s: Scalar) -> Quantity {
 		return Quantity{
 			scalar: s,
-			unit: Unit::new()
+			unit: Unit::new(),
+			uncertainty: None
 		};
 	}
 
+	/// The numeric part of this quantity, in terms of `self.unit()`.
+	pub fn scalar(&self) -> &Scalar { &self.scalar }
+
+	/// The standard uncertainty of this quantity, in terms of `self.unit()`.
+	/// `None` if this quantity is exact.
+	pub fn uncertainty(&self) -> Option<&Scalar> { self.uncertainty.as_ref() }
+
+	/// False if this quantity's uncertainty blew up,
+	/// like `sqrt(0 ± 0.1)`, where the slope is infinite.
+	pub fn uncertainty_is_finite(&self) -> bool {
+		let Some(u) = &self.uncertainty else { return true };
+		return !u.is_nan() && u.to_f64().is_finite();
+	}
+
+	/// The numeric part of this quantity as an `f64`.
+	/// This may lose precision.
+	pub fn to_f64(&self) -> f64 { self.scalar.to_f64() }
+
 	pub fn insert_unit(&mut self, ui: FreeUnit, pi: Scalar) { self.unit.insert(ui, pi) }
 	pub fn set_unit(&mut self, u: Unit) { self.unit = u; }
-	pub fn without_unit(&self) -> Quantity { Quantity::from_scalar(self.scalar.clone()) }
+	pub fn without_unit(&self) -> Quantity {
+		let mut q = Quantity::from_scalar(self.scalar.clone());
+		q.uncertainty = self.uncertainty.clone();
+		return q;
+	}
 
-	pub fn convert_to(&self, other: Quantity) -> Option<Quantity> {
-		if !self.unit.compatible_with(&other.unit) { return None; }
+	/// Add a standard uncertainty of `u` to this quantity.
+	/// `u` must be in the same unit as `self`.
+	/// Returns `None` if `u` is negative.
+	pub fn with_uncertainty(&self, u: Scalar) -> Option<Quantity> {
+		if u.is_negative() || u.is_nan() { return None; }
+		let mut q = self.clone();
+		if !u.is_zero() { q.uncertainty = combine(q.uncertainty, Some(u)); }
+		return Some(q);
+	}
 
-		let n = self.clone();
-		let fa = self.unit.to_base_factor();
-		let fb = other.unit.to_base_factor();
+	/// Convert this quantity to the unit of `other`, ignoring `other`'s value.
+	/// Returns `None` if the units are incompatible.
+	/// Currencies may only be converted if `rates` has a rate for them.
+	pub fn convert_to(&self, other: Quantity, rates: Option<&Rates>) -> Option<Quantity> {
+		if !self.unit.compatible_with(&other.unit, rates) { return None; }
 
-		return Some(n.mul_no_convert(fa).div_no_convert(fb))
+		let n = self.clone();
+		let fa = self.unit.to_base_factor(rates);
+		let fb = other.unit.to_base_factor(rates);
+
+		// The result is in the units of `other`.
+		// Copy them, so units keep the names they were written with.
+		let mut n = n.mul_no_convert(fa).div_no_convert(fb);
+		n.set_unit(other.unit);
+		return Some(n);
 	}
 
-	pub fn match_units(&mut self, other: &Quantity) {
+	pub fn match_units(&mut self, other: &Quantity, rates: Option<&Rates>) {
 
 		let mut new_units = Quantity::new_rational_from_string("1").unwrap();
 		let mut flag;
@@ -135,7 +274,7 @@ impl Quantity {
 			for (uo, _) in other.unit.get_val() {
 				// Use generalized compatible_with check to match reciprocal units
 				// (for example, 1Hz * 1 sec.)
-				let f = Unit::from_free(*uo).compatible_with_power(&Unit::from_free(*us));
+				let f = Unit::from_free(*uo).compatible_with_power(&Unit::from_free(*us), rates);
 				if f.is_none() { continue; }
 				let f = f.unwrap();
 
@@ -150,20 +289,57 @@ impl Quantity {
 		}
 
 		// Convert self to new units
-		*self = self.convert_to(new_units).unwrap();
+		*self = self.convert_to(new_units, rates).unwrap();
 	}
 
-	pub fn convert_to_base(&self) -> Quantity { self.convert_to(self.unit.to_base()).unwrap() }
+	/// Convert this quantity to SI base units.
+	pub fn convert_to_base(&self, rates: Option<&Rates>) -> Quantity { self.convert_to(self.unit.to_base(rates), rates).unwrap() }
+
+	/// Write this quantity in the best unit we know:
+	/// a named unit if one matches (`kg·m/s²` becomes `N`),
+	/// with a prefix that keeps the number readable (`0.0032 m` becomes `3.2 mm`).
+	/// Does nothing if `enable_simplify` is off.
+	pub fn simplify(&self, context: &Context) -> Quantity {
+		if !context.config.enable_simplify || self.unitless() { return self.clone(); }
+		let mut q = self.clone();
+
+		// Only rewrite SI units. `W·h` or `ft·lbf`
+		// were probably written that way on purpose.
+		let targets = WholeUnit::simplify_targets();
+		if q.unit.get_val().len() > 1 && q.unit.get_val().keys().all(|u| targets.contains(&u.whole())) {
+			for w in targets {
+				let mut u = Quantity::new_rational(1f64).unwrap();
+				u.insert_unit(FreeUnit::from_whole(*w), Scalar::new_rational(1f64).unwrap());
+				if let Some(c) = q.convert_to(u, context.get_rates()) { q = c; break; }
+			}
+		}
+
+		// Prefixes only make sense on a single unit
+		if q.unit.get_val().len() != 1 { return q; }
+		let (u, p) = q.unit.get_val().iter().next().unwrap();
+		if *p != Scalar::new_rational(1f64).unwrap() { return q; }
+
+		let b = u.best_prefix(q.to_f64());
+		if b == *u { return q; }
+
+		let mut t = Quantity::new_rational(1f64).unwrap();
+		t.insert_unit(b, Scalar::new_rational(1f64).unwrap());
+		return q.convert_to(t, context.get_rates()).unwrap();
+	}
 }
 
 
+// `$d` is the derivative of `$x`,
+// used to propagate uncertainty.
 macro_rules! quant_foward {
-	( $x:ident ) => {
+	( $x:ident, $d:expr ) => {
 		pub fn $x(&self) -> Quantity {
 			if !self.unitless() { panic!() }
+			let d: fn(&Scalar) -> Scalar = $d;
 			Quantity {
 				scalar: self.scalar.$x(),
-				unit: self.unit.clone()
+				unit: self.unit.clone(),
+				uncertainty: scale(&self.uncertainty, || d(&self.scalar))
 			}
 		}
 	}
@@ -179,72 +355,107 @@ impl Quantity {
 	pub fn unitless(&self) -> bool { self.unit.unitless() }
 	pub fn unit(&self) -> &Unit { &self.unit }
 
-	quant_foward!(fract);
-	quant_foward!(abs);
-	quant_foward!(floor);
-	quant_foward!(ceil);
-	quant_foward!(round);
-	quant_foward!(sin);
-	quant_foward!(cos);
-	quant_foward!(tan);
-	quant_foward!(csc);
-	quant_foward!(sec);
-	quant_foward!(cot);
-	quant_foward!(asin);
-	quant_foward!(acos);
-	quant_foward!(atan);
-	quant_foward!(sinh);
-	quant_foward!(cosh);
-	quant_foward!(tanh);
-	quant_foward!(csch);
-	quant_foward!(sech);
-	quant_foward!(coth);
-	quant_foward!(asinh);
-	quant_foward!(acosh);
-	quant_foward!(atanh);
-	quant_foward!(exp);
-	quant_foward!(ln);
-	quant_foward!(log10);
-	quant_foward!(log2);
+	quant_foward!(fract, |_| one());
+	quant_foward!(abs, |_| one());
+
+	// Rounding is flat between jumps,
+	// so these results are exact.
+	quant_foward!(floor, |_| zero());
+	quant_foward!(ceil, |_| zero());
+	quant_foward!(round, |_| zero());
+
+	quant_foward!(sin, |x| x.cos());
+	quant_foward!(cos, |x| x.sin());
+	quant_foward!(tan, |x| x.sec() * x.sec());
+	quant_foward!(csc, |x| x.csc() * x.cot());
+	quant_foward!(sec, |x| x.sec() * x.tan());
+	quant_foward!(cot, |x| x.csc() * x.csc());
+	quant_foward!(asin, |x| inv_sqrt(one() - x.clone() * x.clone()));
+	quant_foward!(acos, |x| inv_sqrt(one() - x.clone() * x.clone()));
+	quant_foward!(atan, |x| inv(one() + x.clone() * x.clone()));
+	quant_foward!(sinh, |x| x.cosh());
+	quant_foward!(cosh, |x| x.sinh());
+	quant_foward!(tanh, |x| x.sech() * x.sech());
+	quant_foward!(csch, |x| x.csch() * x.coth());
+	quant_foward!(sech, |x| x.sech() * x.tanh());
+	quant_foward!(coth, |x| x.csch() * x.csch());
+	quant_foward!(asinh, |x| inv_sqrt(x.clone() * x.clone() + one()));
+	quant_foward!(acosh, |x| inv_sqrt(x.clone() * x.clone() - one()));
+	quant_foward!(atanh, |x| inv(one() - x.clone() * x.clone()));
+	quant_foward!(exp, |x| x.exp());
+	quant_foward!(ln, |x| inv(x.clone()));
+	quant_foward!(log10, |x| inv(x.clone() * Scalar::new_float(10f64.ln()).unwrap()));
+	quant_foward!(log2, |x| inv(x.clone() * Scalar::new_float(2f64.ln()).unwrap()));
 
 	pub fn log(&self, base: Quantity) -> Quantity {
 		if !self.unitless() { panic!() }
 		Quantity {
 			scalar: self.scalar.log(base.scalar),
-			unit: self.unit.clone()
+			unit: self.unit.clone(),
+			uncertainty: None
 		}
 	}
 
 	pub fn pow(&self, pwr: Quantity) -> Quantity {
+		let x = &self.scalar;
+		let p = &pwr.scalar;
+
+		// d/dx x^p = p x^(p-1), d/dp x^p = x^p ln(x)
+		let uncertainty = combine(
+			scale(&self.uncertainty, || p.clone() * x.pow(p.clone() - one())),
+			scale(&pwr.uncertainty, || x.pow(p.clone()) * x.ln())
+		);
+
 		Quantity {
 			scalar: self.scalar.pow(pwr.scalar.clone()),
-			unit: self.unit.pow(pwr.scalar)
+			unit: self.unit.pow(pwr.scalar),
+			uncertainty
 		}
 	}
 }
 
 
 impl Quantity {
+	// Uncertainty of self * other
+	fn mul_uncertainty(&self, other: &Quantity) -> Option<Scalar> {
+		return combine(
+			scale(&self.uncertainty, || other.scalar.clone()),
+			scale(&other.uncertainty, || self.scalar.clone())
+		);
+	}
+
+	// Uncertainty of self / other
+	fn div_uncertainty(&self, other: &Quantity) -> Option<Scalar> {
+		return combine(
+			scale(&self.uncertainty, || one() / other.scalar.clone()),
+			scale(&other.uncertainty, || self.scalar.clone() / (other.scalar.clone() * other.scalar.clone()))
+		);
+	}
+
 	pub fn mul_no_convert(self, other: Self) -> Self {
 		Quantity {
+			uncertainty: self.mul_uncertainty(&other),
 			scalar: self.scalar * other.scalar,
 			unit: self.unit * other.unit
 		}
 	}
 
 	pub fn mul_assign_no_convert(&mut self, other: Self) {
+		self.uncertainty = self.mul_uncertainty(&other);
 		self.scalar *= other.scalar;
 		self.unit *= other.unit;
 	}
 
 	pub fn div_no_convert(self, other: Self) -> Self {
 		Quantity {
+			uncertainty: self.div_uncertainty(&other),
 			scalar: self.scalar / other.scalar,
 			unit: self.unit / other.unit
 		}
 	}
 
 	pub fn div_assign_no_convert(&mut self, other: Self) {
+		self.uncertainty = self.mul_uncertainty(&other);
 		self.scalar *= other.scalar;
 		self.unit *= other.unit;
 	}
@@ -257,7 +468,8 @@ impl Neg for Quantity where {
 	fn neg(self) -> Self::Output {
 		Quantity {
 			scalar: -self.scalar,
-			unit: self.unit
+			unit: self.unit,
+			uncertainty: self.uncertainty
 		}
 	}
 }
@@ -266,30 +478,32 @@ impl Add for Quantity {
 	type Output = Self;
 
 	fn add(self, other: Self) -> Self::Output {
-		if !self.unit.compatible_with(&other.unit) { panic!("Tried to add incompatible units") }
+		if !self.unit.compatible_with(&other.unit, None) { panic!("Tried to add incompatible units") }
 
 		let mut o = other;
 		if self.unit != o.unit {
-			o = o.convert_to(self.clone()).unwrap();
+			o = o.convert_to(self.clone(), None).unwrap();
 		}
 
 		Quantity {
 			scalar: self.scalar + o.scalar,
-			unit: self.unit
+			unit: self.unit,
+			uncertainty: combine(self.uncertainty, o.uncertainty)
 		}
 	}
 }
 
 impl AddAssign for Quantity where {
 	fn add_assign(&mut self, other: Self) {
-		if !self.unit.compatible_with(&other.unit) { panic!("Tried to addassign incompatible units") }
+		if !self.unit.compatible_with(&other.unit, None) { panic!("Tried to addassign incompatible units") }
 
 		let mut o = other;
 		if self.unit != o.unit {
-			o = o.convert_to(self.clone()).unwrap();
+			o = o.convert_to(self.clone(), None).unwrap();
 		}
 
-		self.scalar += o.scalar
+		self.scalar += o.scalar;
+		self.uncertainty = combine(self.uncertainty.take(), o.uncertainty);
 	}
 }
 
@@ -297,30 +511,32 @@ impl Sub for Quantity {
 	type Output = Self;
 
 	fn sub(self, other: Self) -> Self::Output {
-		if !self.unit.compatible_with(&other.unit) { panic!("Tried to subtract incompatible units") }
+		if !self.unit.compatible_with(&other.unit, None) { panic!("Tried to subtract incompatible units") }
 
 		let mut o = other;
 		if self.unit != o.unit {
-			o = o.convert_to(self.clone()).unwrap();
+			o = o.convert_to(self.clone(), None).unwrap();
 		}
 
 		Quantity {
 			scalar: self.scalar - o.scalar,
-			unit: self.unit
+			unit: self.unit,
+			uncertainty: combine(self.uncertainty, o.uncertainty)
 		}
 	}
 }
 
 impl SubAssign for Quantity where {
 	fn sub_assign(&mut self, other: Self) {
-		if !self.unit.compatible_with(&other.unit) { panic!("Tried to subassign incompatible units") }
+		if !self.unit.compatible_with(&other.unit, None) { panic!("Tried to subassign incompatible units") }
 
 		let mut o = other;
 		if self.unit != o.unit {
-			o = o.convert_to(self.clone()).unwrap();
+			o = o.convert_to(self.clone(), None).unwrap();
 		}
 
 		self.scalar -= o.scalar;
+		self.uncertainty = combine(self.uncertainty.take(), o.uncertainty);
 	}
 }
 
@@ -330,9 +546,10 @@ impl Mul for Quantity {
 	fn mul(self, other: Self) -> Self::Output {
 
 		let mut o = other;
-		o.match_units(&self);
+		o.match_units(&self, None);
 
 		Quantity {
+			uncertainty: self.mul_uncertainty(&o),
 			scalar: self.scalar * o.scalar,
 			unit: self.unit * o.unit
 		}
@@ -343,8 +560,9 @@ impl MulAssign for Quantity where {
 	fn mul_assign(&mut self, other: Self) {
 
 		let mut o = other;
-		o.match_units(&self);
+		o.match_units(&self, None);
 
+		self.uncertainty = self.mul_uncertainty(&o);
 		self.scalar *= o.scalar;
 		self.unit *= o.unit;
 	}
@@ -356,9 +574,10 @@ impl Div for Quantity {
 	fn div(self, other: Self) -> Self::Output {
 
 		let mut o = other;
-		o.match_units(&self);
+		o.match_units(&self, None);
 
 		Quantity {
+			uncertainty: self.div_uncertainty(&o),
 			scalar: self.scalar / o.scalar,
 			unit: self.unit / o.unit
 		}
@@ -369,8 +588,9 @@ impl DivAssign for Quantity where {
 	fn div_assign(&mut self, other: Self) {
 
 		let mut o = other;
-		o.match_units(&self);
+		o.match_units(&self, None);
 
+		self.uncertainty = self.div_uncertainty(&o);
 		self.scalar /= o.scalar;
 		self.unit /= o.unit;
 	}
@@ -383,9 +603,16 @@ impl Rem<Quantity> for Quantity {
 		if !self.unit.unitless() { panic!("Tried to % a quantity with units") }
 		if !other.unit.unitless() { panic!("Tried to % by a quantity with units") }
 
+		// d/db (a % b) = -floor(a / b)
+		let uncertainty = combine(
+			self.uncertainty.clone(),
+			scale(&other.uncertainty, || (self.scalar.clone() / other.scalar.clone()).floor())
+		);
+
 		Quantity {
 			scalar: self.scalar % other.scalar,
-			unit: self.unit
+			unit: self.unit,
+			uncertainty
 		}
 	}
 }
@@ -76,6 +76,7 @@ impl ScalarBase for F64Base {
 	fn is_negative(&self) -> bool { self.val.is_sign_negative() }
 	fn is_positive(&self) -> bool { self.val.is_sign_positive() }
 	fn is_int(&self) -> bool { self.val.floor() == self.val }
+	fn to_f64(&self) -> f64 { self.val }
 
 	foward!(abs);
 	foward!(floor);
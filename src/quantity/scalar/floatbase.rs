@@ -88,6 +88,7 @@ impl ScalarBase for FloatBase {
 	fn is_positive(&self) -> bool { self.val.sign() == num::bigint::Sign::Plus }
 
 	fn is_int(&self) -> bool { self.val.is_integer() }
+	fn to_f64(&self) -> f64 { self.val.to_string().parse().unwrap_or(f64::NAN) }
 
 	fn abs(&self) -> Option<FloatBase> { Some(FloatBase{ val: self.val.abs() }) }
 	fn round(&self) -> Option<FloatBase> { Some(FloatBase{ val: self.val.round(0) }) }
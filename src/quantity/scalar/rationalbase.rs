@@ -2,6 +2,7 @@ use num::rational::BigRational;
 use num::BigInt;
 use num::Num;
 use num::Signed;
+use num::ToPrimitive;
 
 use std::ops::{
 	Add, Sub, Mul, Div,
@@ -103,6 +104,7 @@ impl ScalarBase for RationalBase {
 	fn is_one(&self) -> bool {self.val == BigRational::from_integer(BigInt::from(1))}
 	fn is_negative(&self) -> bool { self.val.is_negative() }
 	fn is_positive(&self) -> bool { self.val.is_positive() }
+	fn to_f64(&self) -> f64 { self.val.to_f64().unwrap_or(f64::NAN) }
 
 	fn abs(&self) -> Option<RationalBase> {Some(RationalBase{val: self.val.clone().abs()})}
 	fn floor(&self) -> Option<RationalBase> {Some(RationalBase{val: self.val.clone().floor()})}
@@ -9,6 +9,7 @@ use std::cmp::Ordering;
 
 use super::FloatBase as FloatBase;
 use super::rationalbase::RationalBase;
+use num::{Zero, ToPrimitive};
 
 
 pub trait ScalarBase:
@@ -30,6 +31,7 @@ pub trait ScalarBase:
 	fn is_int(&self) -> bool;
 	fn is_negative(&self) -> bool;
 	fn is_positive(&self) -> bool;
+	fn to_f64(&self) -> f64;
 
 	// Mathematical
 	fn exp(&self) -> Option<Self>;
@@ -203,6 +205,14 @@ impl Scalar {
 		}
 	}
 
+	// Lossy conversion, for handing values to other code.
+	pub fn to_f64(&self) -> f64 {
+		match self {
+			Scalar::Rational{v} => v.to_f64(),
+			Scalar::Float{v} => v.to_f64(),
+		}
+	}
+
 	scalar_foward!(fract);
 	scalar_foward!(abs);
 	scalar_foward!(floor);
@@ -240,7 +250,19 @@ impl Scalar {
 
 	pub fn pow(&self, base: Scalar) -> Scalar {
 		match self {
-			Scalar::Rational{..} => {
+			Scalar::Rational{v} => {
+				// Small integer powers of rationals are still rational.
+				// This keeps unit conversions (like ft to in) exact.
+				if let Scalar::Rational{v: p} = &base {
+					if p.val.is_integer() && !v.val.is_zero() {
+						if let Some(p) = p.val.to_integer().to_i32() {
+							if p.abs() <= 64 {
+								return wrap_rational!(RationalBase{val: v.val.pow(p)});
+							}
+						}
+					}
+				}
+
 				let a = match to_float(self.clone()) {
 					Scalar::Rational{..} => panic!(),
 					Scalar::Float{v} => v,
@@ -0,0 +1,92 @@
+use crate::quantity::Scalar;
+use crate::quantity::Quantity;
+use super::WholeUnit;
+use super::FreeUnit;
+use super::Unit;
+use super::freeunit_from_string;
+
+
+/// A table of exchange rates.
+/// Each rate is the price of one unit of a currency in US dollars.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Rates {
+	/// Where these rates came from, usually a file name.
+	pub source: String,
+
+	/// When these rates were last updated, if we know.
+	pub updated: Option<String>,
+
+	pub rates: Vec<(WholeUnit, Scalar)>
+}
+
+impl Rates {
+	/// Read exchange rates from a CSV file.
+	///
+	/// Each line looks like `EUR,1.08`, which means one euro costs $1.08.
+	/// A line like `updated,2024-05-01` tells us when these rates were updated.
+	/// Empty lines and lines starting with `#` are ignored.
+	pub fn from_csv(source: &str, s: &str) -> Result<Rates, String> {
+		let mut r = Rates {
+			source: source.to_string(),
+			updated: None,
+			rates: Vec::new()
+		};
+
+		for (i, l) in s.lines().enumerate() {
+			let l = l.trim();
+			if l.len() == 0 || l.starts_with('#') { continue; }
+
+			let Some((a, b)) = l.split_once(',') else {
+				return Err(format!("line {}: expected `code,rate`", i + 1));
+			};
+			let a = a.trim();
+			let b = b.trim();
+
+			if a == "updated" {
+				r.updated = Some(b.to_string());
+				continue;
+			}
+
+			let Some(u) = freeunit_from_string(a) else {
+				return Err(format!("line {}: unknown currency `{a}`", i + 1));
+			};
+			let u = u.whole();
+
+			// Rates are in dollars, so the dollar is always 1.
+			if u == WholeUnit::Usd { continue; }
+			if !WholeUnit::rate_units().contains(&u) {
+				return Err(format!("line {}: `{a}` isn't a currency", i + 1));
+			}
+
+			let v = Scalar::new_rational_from_string(b);
+			if v.is_none() || !v.as_ref().unwrap().is_positive() {
+				return Err(format!("line {}: bad rate `{b}`", i + 1));
+			}
+
+			r.rates.push((u, v.unwrap()));
+		}
+
+		return Ok(r);
+	}
+
+	/// The rate for currency `u`, if we have one.
+	pub fn get(&self, u: WholeUnit) -> Option<&Scalar> {
+		return self.rates.iter().find(|(x, _)| *x == u).map(|(_, v)| v);
+	}
+}
+
+
+// The base factor of currency `u`, in units of `base`.
+// If we don't have a rate for `u`, it has no base factor.
+// This makes it incompatible with every other currency.
+pub (super) fn rate_factor(rates: Option<&Rates>, u: WholeUnit, base: WholeUnit) -> Option<Quantity> {
+	let v = rates?.get(u)?;
+
+	let mut q = Quantity::from_scalar(v.clone());
+	q.set_unit(Unit::from_array(&[
+		(FreeUnit::from_whole(u), Scalar::new_rational(-1f64).unwrap()),
+		(FreeUnit::from_whole(base), Scalar::new_rational(1f64).unwrap())
+	]));
+	return Some(q);
+}
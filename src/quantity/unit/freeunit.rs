@@ -1,23 +1,48 @@
 use std::hash::Hash;
+use std::hash::Hasher;
 
 use crate::quantity::Scalar;
 use crate::quantity::Quantity;
 use super::WholeUnit;
 use super::Prefix;
+use super::Rates;
 
 
 #[derive(Debug)]
-#[derive(Hash)]
 #[derive(Copy, Clone)]
-#[derive(PartialEq, Eq)]
 pub struct FreeUnit {
 	pub (in super) whole: WholeUnit,
-	pub (in super) prefix: Prefix
+	pub (in super) prefix: Prefix,
+
+	// If this unit was written with a long prefix (like `kilometers`),
+	// this is the unit's name without that prefix (`meters`).
+	// This only changes how we print this unit.
+	pub (in super) long: Option<&'static str>
 }
 
+// `long` is ignored here, `km` and `kilometer` are the same unit.
+impl Hash for FreeUnit {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.whole.hash(state);
+		self.prefix.hash(state);
+	}
+}
+
+impl PartialEq for FreeUnit {
+	fn eq(&self, other: &Self) -> bool {
+		return self.whole == other.whole && self.prefix == other.prefix;
+	}
+}
+
+impl Eq for FreeUnit {}
+
 
 impl ToString for FreeUnit {
 	fn to_string(&self) -> String {
+		if let Some(s) = self.long {
+			let p = self.prefix.to_long_string();
+			return format!("{p}{s}");
+		}
 
 		let s = self.whole.to_string();
 		let p = self.prefix.to_string();
@@ -27,19 +52,83 @@ impl ToString for FreeUnit {
 }
 
 impl FreeUnit {
+	// Like to_string(), but never uses unicode.
+	pub fn to_ascii_string(&self) -> String {
+		if self.long.is_some() { return self.to_string(); }
+
+		let s = self.whole.to_ascii_string();
+		let p = self.prefix.to_string();
+
+		format!("{p}{s}")
+	}
+
 	pub fn from_whole(whole: WholeUnit) -> FreeUnit {
-		return FreeUnit { whole, prefix: Prefix::None }
+		return FreeUnit { whole, prefix: Prefix::None, long: None }
+	}
+
+	// Returns this unit with the prefix that puts a value of `x`
+	// (in this unit) between 1 and 1000, like `0.0032 m` => `3.2 mm`.
+	// Returns a copy of this unit if there is no such prefix.
+	pub fn best_prefix(&self, x: f64) -> FreeUnit {
+		let x = x.abs();
+		if x == 0.0 || !x.is_finite() { return *self; }
+
+		// Keep binary prefixes binary, like `4096 KiB` => `4 MiB`
+		if let Some(e) = self.prefix.binary_exponent().filter(|_| self.prefix != Prefix::None) {
+			if (1.0..1024.0).contains(&x) { return *self; }
+
+			let t = ((x.log2().floor() as i32 + e).div_euclid(10) * 10).clamp(0, 80);
+			let Some(p) = Prefix::from_binary_exponent(t) else { return *self };
+			return self.with_prefix(self.whole, p);
+		}
+
+		// Prefixes don't read well on every unit, like `kK` or `mlm`.
+		if !self.whole.reprefix() { return *self; }
+
+		if (1.0..1000.0).contains(&x) { return *self; }
+		let Some(e) = self.prefix.exponent() else { return *self };
+
+		// Magnitude of x without a prefix, rounded down to a multiple of three
+		let mut t = (x.log10().floor() as i32 + e).div_euclid(3) * 3;
+		t = t.clamp(-30, 30);
+
+		// Kiloseconds are rarely used, hours and days are better.
+		if self.whole == WholeUnit::Second { t = t.min(0); }
+
+		// Tonnes read better than megagrams, and grams better than millitonnes.
+		// Long names keep their unit, since we don't know how to spell the other one.
+		let mut whole = self.whole;
+		if self.long.is_some() {
+			if whole == WholeUnit::Gram { t = t.min(3); }
+		} else if whole == WholeUnit::Gram && t >= 6 {
+			whole = WholeUnit::Tonne;
+			t -= 6;
+		} else if whole == WholeUnit::Tonne && t < 0 {
+			whole = WholeUnit::Gram;
+			t += 6;
+		}
+
+		let Some(p) = Prefix::from_exponent(t) else { return *self };
+		return self.with_prefix(whole, p);
+	}
+
+	// `whole` with prefix `p`, written like this unit.
+	// Returns a copy of this unit if `whole` doesn't take `p`.
+	fn with_prefix(&self, whole: WholeUnit, p: Prefix) -> FreeUnit {
+		if !whole.prefixes(self.long).contains(&p) { return *self; }
+		return FreeUnit { whole, prefix: p, long: self.long };
 	}
 
-	pub fn from_whole_prefix(whole: WholeUnit, prefix: Prefix) -> FreeUnit { FreeUnit {whole, prefix} }
+	pub fn from_whole_prefix(whole: WholeUnit, prefix: Prefix) -> FreeUnit { FreeUnit {whole, prefix, long: None} }
 	pub fn set_prefix(&mut self, prefix: Prefix) { self.prefix = prefix; }
 	pub fn get_prefix(&self) -> Prefix { self.prefix }
+	pub fn whole(&self) -> WholeUnit { self.whole }
 
 	/// Returns a quantity q, so that self * q
 	/// gives a quantity in base units.
-	pub fn to_base_factor(&self) -> Quantity {
+	pub fn to_base_factor(&self, rates: Option<&Rates>) -> Quantity {
 
-		let q = self.whole.base_factor();
+		let q = self.whole.base_factor(rates);
 		let mut q = q.unwrap_or(Quantity::new_rational_from_string("1").unwrap());
 
 		let mut p = self.prefix.to_ratio();
@@ -51,8 +140,8 @@ impl FreeUnit {
 	}
 
 	// Get this unit in terms of base units
-	pub fn to_base(&self) -> Quantity {
-		let f = self.to_base_factor();
+	pub fn to_base(&self, rates: Option<&Rates>) -> Quantity {
+		let f = self.to_base_factor(rates);
 
 		let mut q = Quantity::new_rational_from_string("1").unwrap();
 		q.insert_unit(FreeUnit::from_whole_prefix(self.whole, self.prefix), Scalar::new_rational(1f64).unwrap());
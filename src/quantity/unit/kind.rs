@@ -0,0 +1,70 @@
+use super::WholeUnit;
+use super::FreeUnit;
+use super::Unit;
+use super::Rates;
+use crate::quantity::Scalar;
+
+
+// Names of quantities with known dimensions.
+// Dimensions are written in base units.
+const KINDS: &[(&str, &[(WholeUnit, i64)])] = &[
+	("dimensionless", &[]),
+	("length", &[(WholeUnit::Meter, 1)]),
+	("mass", &[(WholeUnit::Gram, 1)]),
+	("time", &[(WholeUnit::Second, 1)]),
+	("electric current", &[(WholeUnit::Ampere, 1)]),
+	("temperature", &[(WholeUnit::Kelvin, 1)]),
+	("amount of substance", &[(WholeUnit::Mole, 1)]),
+	("luminous intensity", &[(WholeUnit::Candela, 1)]),
+
+	("area", &[(WholeUnit::Meter, 2)]),
+	("volume", &[(WholeUnit::Meter, 3)]),
+	("frequency", &[(WholeUnit::Second, -1)]),
+	("speed", &[(WholeUnit::Meter, 1), (WholeUnit::Second, -1)]),
+	("acceleration", &[(WholeUnit::Meter, 1), (WholeUnit::Second, -2)]),
+	("density", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, -3)]),
+	("momentum", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 1), (WholeUnit::Second, -1)]),
+	("force", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 1), (WholeUnit::Second, -2)]),
+	("pressure", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, -1), (WholeUnit::Second, -2)]),
+	("energy", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 2), (WholeUnit::Second, -2)]),
+	("power", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 2), (WholeUnit::Second, -3)]),
+	("absorbed dose", &[(WholeUnit::Meter, 2), (WholeUnit::Second, -2)]),
+	("catalytic activity", &[(WholeUnit::Mole, 1), (WholeUnit::Second, -1)]),
+
+	("electric charge", &[(WholeUnit::Ampere, 1), (WholeUnit::Second, 1)]),
+	("voltage", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 2), (WholeUnit::Second, -3), (WholeUnit::Ampere, -1)]),
+	("resistance", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 2), (WholeUnit::Second, -3), (WholeUnit::Ampere, -2)]),
+	("conductance", &[(WholeUnit::Gram, -1), (WholeUnit::Meter, -2), (WholeUnit::Second, 3), (WholeUnit::Ampere, 2)]),
+	("capacitance", &[(WholeUnit::Gram, -1), (WholeUnit::Meter, -2), (WholeUnit::Second, 4), (WholeUnit::Ampere, 2)]),
+	("inductance", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 2), (WholeUnit::Second, -2), (WholeUnit::Ampere, -2)]),
+	("magnetic flux", &[(WholeUnit::Gram, 1), (WholeUnit::Meter, 2), (WholeUnit::Second, -2), (WholeUnit::Ampere, -1)]),
+	("magnetic flux density", &[(WholeUnit::Gram, 1), (WholeUnit::Second, -2), (WholeUnit::Ampere, -1)]),
+
+	("angle", &[(WholeUnit::Degree, 1)]),
+	("angular velocity", &[(WholeUnit::Degree, 1), (WholeUnit::Second, -1)]),
+	("solid angle", &[(WholeUnit::Steradian, 1)]),
+	("luminous flux", &[(WholeUnit::Candela, 1), (WholeUnit::Steradian, 1)]),
+	("illuminance", &[(WholeUnit::Candela, 1), (WholeUnit::Steradian, 1), (WholeUnit::Meter, -2)]),
+	("luminance", &[(WholeUnit::Candela, 1), (WholeUnit::Meter, -2)]),
+
+	("information", &[(WholeUnit::Bit, 1)]),
+	("data rate", &[(WholeUnit::Bit, 1), (WholeUnit::Second, -1)]),
+	("money", &[(WholeUnit::Usd, 1)]),
+];
+
+
+impl Unit {
+	/// The name of the quantity this unit measures,
+	/// like "force" for newtons. None if we don't know.
+	pub fn kind(&self, rates: Option<&Rates>) -> Option<&'static str> {
+		let b = self.to_base(rates).unit;
+
+		let (k, _) = KINDS.iter().find(|(_, k)| {
+			b == Unit::from_array(&k.iter().map(
+				|(w, p)| (FreeUnit::from_whole(*w), Scalar::new_rational(*p as f64).unwrap())
+			).collect::<Vec<_>>())
+		})?;
+
+		return Some(k);
+	}
+}
@@ -3,13 +3,28 @@ use std::hash::Hash;
 mod freeunit;
 mod prefix;
 mod unit;
+mod currency;
+mod kind;
 
 pub use prefix::Prefix;
 pub use unit::Unit;
 pub use freeunit::FreeUnit;
+pub use currency::Rates;
 
 use crate::quantity::Quantity;
 use crate::quantity::Scalar;
+use crate::parser::Constant;
+use crate::parser::Expression;
 
 use prefix::str_to_prefix;
+use currency::rate_factor;
+
+
+// The value of a constant, for units defined with `base_constants`.
+// These constants must be plain numbers.
+fn constant_scalar(c: Constant) -> Scalar {
+	let Expression::Quantity(_, q) = c.value() else { panic!("Constant {c:?} isn't a number") };
+	return q.scalar;
+}
+
 include!(concat!(env!("OUT_DIR"), "/units.rs"));
\ No newline at end of file
@@ -35,6 +35,8 @@ pub enum Prefix {
 	Quecto,
 
 
+	BinYotta,
+	BinZetta,
 	BinExa,
 	BinPeta,
 	BinTera,
@@ -74,6 +76,8 @@ impl Prefix {
 			Prefix::Quecto => "1e-30",
 
 
+			Prefix::BinYotta => "1208925819614629174706176", // 2^80
+			Prefix::BinZetta => "1180591620717411303424", // 2^70
 			Prefix::BinExa => "1152921504606846976", // 2^60
 			Prefix::BinPeta => "1125899906842624", // 2^50
 			Prefix::BinTera => "1099511627776", // 2^40
@@ -89,6 +93,103 @@ impl Prefix {
 	}
 }
 
+impl Prefix {
+	// This prefix is 10^exponent.
+	// None for binary prefixes.
+	pub fn exponent(&self) -> Option<i32> {
+		return Some(match self {
+			Prefix::Quetta => 30,
+			Prefix::Ronna => 27,
+			Prefix::Yotta => 24,
+			Prefix::Zetta => 21,
+			Prefix::Exa => 18,
+			Prefix::Peta => 15,
+			Prefix::Tera => 12,
+			Prefix::Giga => 9,
+			Prefix::Mega => 6,
+			Prefix::Kilo => 3,
+			Prefix::Hecto => 2,
+			Prefix::Deka => 1,
+			Prefix::None => 0,
+			Prefix::Deci => -1,
+			Prefix::Centi => -2,
+			Prefix::Milli => -3,
+			Prefix::Micro => -6,
+			Prefix::Nano => -9,
+			Prefix::Pico => -12,
+			Prefix::Femto => -15,
+			Prefix::Atto => -18,
+			Prefix::Zepto => -21,
+			Prefix::Yocto => -24,
+			Prefix::Ronto => -27,
+			Prefix::Quecto => -30,
+			_ => return None
+		});
+	}
+
+	// The prefix for 10^e, if e is a multiple of 3.
+	// Hecto, deka, deci and centi are never returned.
+	pub fn from_exponent(e: i32) -> Option<Prefix> {
+		return Some(match e {
+			30 => Prefix::Quetta,
+			27 => Prefix::Ronna,
+			24 => Prefix::Yotta,
+			21 => Prefix::Zetta,
+			18 => Prefix::Exa,
+			15 => Prefix::Peta,
+			12 => Prefix::Tera,
+			9 => Prefix::Giga,
+			6 => Prefix::Mega,
+			3 => Prefix::Kilo,
+			0 => Prefix::None,
+			-3 => Prefix::Milli,
+			-6 => Prefix::Micro,
+			-9 => Prefix::Nano,
+			-12 => Prefix::Pico,
+			-15 => Prefix::Femto,
+			-18 => Prefix::Atto,
+			-21 => Prefix::Zepto,
+			-24 => Prefix::Yocto,
+			-27 => Prefix::Ronto,
+			-30 => Prefix::Quecto,
+			_ => return None
+		});
+	}
+
+	// This prefix is 2^exponent.
+	// None for decimal prefixes.
+	pub fn binary_exponent(&self) -> Option<i32> {
+		return Some(match self {
+			Prefix::BinYotta => 80,
+			Prefix::BinZetta => 70,
+			Prefix::BinExa => 60,
+			Prefix::BinPeta => 50,
+			Prefix::BinTera => 40,
+			Prefix::BinGiga => 30,
+			Prefix::BinMega => 20,
+			Prefix::BinKilo => 10,
+			Prefix::None => 0,
+			_ => return None
+		});
+	}
+
+	// The binary prefix for 2^e, if e is a multiple of 10.
+	pub fn from_binary_exponent(e: i32) -> Option<Prefix> {
+		return Some(match e {
+			80 => Prefix::BinYotta,
+			70 => Prefix::BinZetta,
+			60 => Prefix::BinExa,
+			50 => Prefix::BinPeta,
+			40 => Prefix::BinTera,
+			30 => Prefix::BinGiga,
+			20 => Prefix::BinMega,
+			10 => Prefix::BinKilo,
+			0 => Prefix::None,
+			_ => return None
+		});
+	}
+}
+
 macro_rules! str_to_prefix {
 	("") => {Prefix::None};
 	("Q") => {Prefix::Quetta};
@@ -115,12 +216,47 @@ macro_rules! str_to_prefix {
 	("y") => {Prefix::Yocto};
 	("r") => {Prefix::Ronto};
 	("q") => {Prefix::Quecto};
+	("Yi") => {Prefix::BinYotta};
+	("Zi") => {Prefix::BinZetta};
 	("Ei") => {Prefix::BinExa};
 	("Pi") => {Prefix::BinPeta};
 	("Ti") => {Prefix::BinTera};
 	("Gi") => {Prefix::BinGiga};
 	("Mi") => {Prefix::BinMega};
 	("Ki") => {Prefix::BinKilo};
+
+	("quetta") => {Prefix::Quetta};
+	("ronna") => {Prefix::Ronna};
+	("yotta") => {Prefix::Yotta};
+	("zetta") => {Prefix::Zetta};
+	("exa") => {Prefix::Exa};
+	("peta") => {Prefix::Peta};
+	("tera") => {Prefix::Tera};
+	("giga") => {Prefix::Giga};
+	("mega") => {Prefix::Mega};
+	("kilo") => {Prefix::Kilo};
+	("hecto") => {Prefix::Hecto};
+	("deca") => {Prefix::Deka};
+	("deci") => {Prefix::Deci};
+	("centi") => {Prefix::Centi};
+	("milli") => {Prefix::Milli};
+	("micro") => {Prefix::Micro};
+	("nano") => {Prefix::Nano};
+	("pico") => {Prefix::Pico};
+	("femto") => {Prefix::Femto};
+	("atto") => {Prefix::Atto};
+	("zepto") => {Prefix::Zepto};
+	("yocto") => {Prefix::Yocto};
+	("ronto") => {Prefix::Ronto};
+	("quecto") => {Prefix::Quecto};
+	("yobi") => {Prefix::BinYotta};
+	("zebi") => {Prefix::BinZetta};
+	("exbi") => {Prefix::BinExa};
+	("pebi") => {Prefix::BinPeta};
+	("tebi") => {Prefix::BinTera};
+	("gibi") => {Prefix::BinGiga};
+	("mebi") => {Prefix::BinMega};
+	("kibi") => {Prefix::BinKilo};
 }
 pub (super) use str_to_prefix;
 
@@ -155,6 +291,8 @@ impl ToString for Prefix {
 			Prefix::Quecto => "q",
 
 
+			Prefix::BinYotta => "Yi",
+			Prefix::BinZetta => "Zi",
 			Prefix::BinExa => "Ei",
 			Prefix::BinPeta => "Pi",
 			Prefix::BinTera => "Ti",
@@ -165,4 +303,50 @@ impl ToString for Prefix {
 			Prefix::None => ""
 		})
 	}
-}
\ No newline at end of file
+}
+
+impl Prefix {
+	// The full name of this prefix, like `kilo`.
+	// Used for units written with long prefixes.
+	pub fn to_long_string(&self) -> String {
+		String::from(match self {
+			Prefix::Quetta => "quetta",
+			Prefix::Ronna => "ronna",
+			Prefix::Yotta => "yotta",
+			Prefix::Zetta => "zetta",
+			Prefix::Exa => "exa",
+			Prefix::Peta => "peta",
+			Prefix::Tera => "tera",
+			Prefix::Giga => "giga",
+			Prefix::Mega => "mega",
+			Prefix::Kilo => "kilo",
+			Prefix::Hecto => "hecto",
+			Prefix::Deka => "deca",
+
+			Prefix::Deci => "deci",
+			Prefix::Centi => "centi",
+			Prefix::Milli => "milli",
+			Prefix::Micro => "micro",
+			Prefix::Nano => "nano",
+			Prefix::Pico => "pico",
+			Prefix::Femto => "femto",
+			Prefix::Atto => "atto",
+			Prefix::Zepto => "zepto",
+			Prefix::Yocto => "yocto",
+			Prefix::Ronto => "ronto",
+			Prefix::Quecto => "quecto",
+
+
+			Prefix::BinYotta => "yobi",
+			Prefix::BinZetta => "zebi",
+			Prefix::BinExa => "exbi",
+			Prefix::BinPeta => "pebi",
+			Prefix::BinTera => "tebi",
+			Prefix::BinGiga => "gibi",
+			Prefix::BinMega => "mebi",
+			Prefix::BinKilo => "kibi",
+
+			Prefix::None => ""
+		})
+	}
+}
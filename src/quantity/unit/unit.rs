@@ -8,6 +8,7 @@ use crate::context::Context;
 use crate::quantity::Scalar;
 use crate::quantity::Quantity;
 use super::FreeUnit;
+use super::Rates;
 use super::freeunit_from_string;
 
 #[derive(Debug)]
@@ -31,13 +32,18 @@ impl Unit {
 		let Some((mut u, mut p)) = i.next() else { panic!() };
 		let mut done = false;
 
+		let name = |u: &FreeUnit| {
+			if context.config.enable_unicode { u.to_string() } else { u.to_ascii_string() }
+		};
+		let sep = if context.config.enable_unicode { '·' } else { '*' };
+
 		// Positive powers
 		let mut t = String::new();
 		while p.is_positive() && !done {
-			let c = u.to_string();
+			let c = name(u);
 
 			if *p == Scalar::new_rational(1f64).unwrap() {
-				t.push_str(&format!("{c}·"));
+				t.push_str(&format!("{c}{sep}"));
 			} else if {
 				context.config.enable_super_powers &&
 				p.is_int() &&
@@ -60,9 +66,9 @@ impl Unit {
 						_ => unreachable!()
 					});
 				}
-				t.push('·');
+				t.push(sep);
 			} else {
-				t.push_str(&format!("{c}^{}·", p.to_string()));
+				t.push_str(&format!("{c}^{}{sep}", p.to_string()));
 			}
 
 			if let Some((a, b)) = i.next() {
@@ -74,11 +80,11 @@ impl Unit {
 		let mut b = String::new();
 		let mut bottom_count = 0;
 		while !done {
-			let c = u.to_string();
+			let c = name(u);
 
 			bottom_count += 1;
 			if t.len() != 0 && *p == Scalar::new_rational(-1f64).unwrap() {
-				b.push_str(&format!("{c}·"));
+				b.push_str(&format!("{c}{sep}"));
 			} else if {
 				context.config.enable_super_powers &&
 				p.is_int() &&
@@ -102,9 +108,11 @@ impl Unit {
 						_ => unreachable!()
 					});
 				}
-				b.push('·');
+				b.push(sep);
 			} else {
-				b.push_str(&format!("{c}^{}·", p.to_string()));
+				let p = p.to_string();
+				let p = if t.len() != 0 { p.trim_start_matches('-') } else { &p };
+				b.push_str(&format!("{c}^{p}{sep}"));
 			}
 
 			if let Some((a, b)) = i.next() {
@@ -112,16 +120,18 @@ impl Unit {
 			} else {done = true}
 		};
 
-		// Slice cuts off the last `·` (2 bytes)
+		// Cut off the last separator
+		t.pop();
+		b.pop();
 		if t.len() == 0 {
-			return format!("{}", &b[..b.len() - 2]);
+			return b;
 		} else if b.len() == 0 {
-			return String::from(&t[..t.len() - 2]);
+			return t;
 		} else {
 			if bottom_count > 1 {
-				return format!("{}/({})", &t[..t.len() - 2], &b[..b.len() - 2] );
+				return format!("{t}/({b})");
 			} else {
-				return format!("{}/{}", &t[..t.len() - 2], &b[..b.len() - 2] );
+				return format!("{t}/{b}");
 			}
 		}
 
@@ -148,9 +158,15 @@ impl Unit {
 	pub fn get_val_mut(&mut self) -> &mut HashMap<FreeUnit, Scalar> { &mut self.val }
 	pub fn unitless(&self) -> bool { self.get_val().len() == 0 }
 
-	pub fn no_space(&self) -> bool {
+	// Should this unit be printed right after its number?
+	// ASCII names (like `deg`) always get a space.
+	pub fn no_space(&self, context: &Context) -> bool {
 		if self.get_val().len() == 1 {
-			return self.get_val().keys().next().unwrap().whole.no_space();
+			let w = self.get_val().keys().next().unwrap().whole;
+			return w.no_space() && (
+				context.config.enable_unicode ||
+				w.to_ascii_string() == w.to_string()
+			);
 		} else { return false; }
 	}
 
@@ -164,9 +180,9 @@ impl Unit {
 
 	// True if base units are the same
 	// compatible <=> can be converted to
-	pub fn compatible_with(&self, other: &Unit) -> bool {
-		let s = self.clone() * self.to_base_factor().unit;
-		let o = other.clone() * other.to_base_factor().unit;
+	pub fn compatible_with(&self, other: &Unit, rates: Option<&Rates>) -> bool {
+		let s = self.clone() * self.to_base_factor(rates).unit;
+		let o = other.clone() * other.to_base_factor(rates).unit;
 
 		return o == s;
 	}
@@ -175,12 +191,12 @@ impl Unit {
 	// True if all base units are the same AND there is a constant factor between their powers.
 	// This is a generalization of `compatible_with`. `compatible_with` is true iff
 	// `compatible_with_power` is one.
-	pub fn compatible_with_power(&self, other: &Unit) -> Option<Scalar> {
+	pub fn compatible_with_power(&self, other: &Unit, rates: Option<&Rates>) -> Option<Scalar> {
 		let mut flag;
 		let mut pow_factor: Option<Scalar> = None;
 
-		let sbu = self.to_base().unit;
-		let obu = other.to_base().unit;
+		let sbu = self.to_base(rates).unit;
+		let obu = other.to_base(rates).unit;
 
 		for (us, ps) in sbu.get_val() {
 			flag = false;
@@ -243,11 +259,11 @@ impl Unit {
 	}
 
 	// Returns a unit `u` so that `self * u` contains only base units.
-	pub fn to_base_factor(&self) -> Quantity {
+	pub fn to_base_factor(&self, rates: Option<&Rates>) -> Quantity {
 		let mut q = Quantity::new_rational(1f64).unwrap();
 
 		for (u, p) in self.get_val().iter() {
-			let b = u.to_base_factor();
+			let b = u.to_base_factor(rates);
 			q.mul_assign_no_convert(b.pow(Quantity::from_scalar(p.clone())));
 		}
 
@@ -255,11 +271,11 @@ impl Unit {
 	}
 
 	// Returns a unit `u` equivalent to `self` that contains only base units.
-	pub fn to_base(&self) -> Quantity {
+	pub fn to_base(&self, rates: Option<&Rates>) -> Quantity {
 		let mut q = Quantity::new_rational(1f64).unwrap();
 
 		for (u, p) in self.get_val().iter() {
-			let b = u.to_base();
+			let b = u.to_base(rates);
 			q.mul_assign_no_convert(b.pow(Quantity::from_scalar(p.clone())));
 		}
 
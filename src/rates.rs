@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use daisycalc::quantity::Rates;
+
+
+// Where we look for exchange rates if --rates isn't given
+pub fn default_path(var: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+	if let Some(d) = var("XDG_CONFIG_HOME").filter(|x| x.len() != 0) {
+		return Some(PathBuf::from(d).join("daisy").join("rates.csv"));
+	}
+
+	let h = var("HOME").filter(|x| x.len() != 0)?;
+	return Some(PathBuf::from(h).join(".config").join("daisy").join("rates.csv"));
+}
+
+
+// Read the rates in `path`.
+// If the file doesn't say when it was updated, use its modification date.
+pub fn load(path: &PathBuf) -> Result<Rates, String> {
+	let s = fs::read_to_string(path).map_err(|e| e.to_string())?;
+	let mut r = Rates::from_csv(&path.display().to_string(), &s)?;
+
+	if r.updated.is_none() {
+		r.updated = fs::metadata(path).ok()
+			.and_then(|m| m.modified().ok())
+			.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+			.map(|d| date_string(d.as_secs() as i64 / 86400));
+	}
+
+	return Ok(r);
+}
+
+
+// Format `days` after 1970-01-01 as YYYY-MM-DD.
+// See http://howardhinnant.github.io/date_algorithms.html
+fn date_string(days: i64) -> String {
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+
+	let d = doy - (153 * mp + 2) / 5 + 1;
+	let m = if mp < 10 { mp + 3 } else { mp - 9 };
+	let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+
+	return format!("{y:04}-{m:02}-{d:02}");
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::PathBuf;
+
+// Detects what the terminal we're running in can do.
+// `var` reads an environment variable, and is
+// usually `|x| std::env::var(x).ok()`.
+
+
+
+// Does the locale say this terminal can show unicode?
+// Uses the first of LC_ALL, LC_CTYPE and LANG that is set.
+pub fn locale_is_unicode(var: &dyn Fn(&str) -> Option<String>) -> bool {
+	for v in ["LC_ALL", "LC_CTYPE", "LANG"] {
+		let Some(s) = var(v) else { continue };
+		if s.len() == 0 { continue; }
+
+		let s = s.to_lowercase();
+		return s.contains("utf-8") || s.contains("utf8");
+	}
+	return false;
+}
+
+
+// Which term_color_type should we use?
+// `tty` should be false if stdout isn't a terminal.
+pub fn color_type(var: &dyn Fn(&str) -> Option<String>, tty: bool) -> u8 {
+	if !tty { return 0; }
+
+	// See no-color.org
+	if var("NO_COLOR").is_some_and(|x| x.len() != 0) { return 0; }
+
+	let term = var("TERM").unwrap_or_default();
+	if term.len() == 0 || term == "dumb" { return 0; }
+
+	let colorterm = var("COLORTERM").unwrap_or_default();
+	if colorterm == "truecolor" || colorterm == "24bit" { return 2; }
+
+	// If this terminal isn't in the terminfo database,
+	// guess from its name.
+	let colors = terminfo_colors(var, &term).unwrap_or(
+		if term.contains("256color") { 256 } else { 8 }
+	);
+
+	if colors >= 256 {
+		return 2;
+	} else if colors >= 8 {
+		return 1;
+	} else {
+		return 0;
+	}
+}
+
+
+// Where terminfo entries may be, in the order ncurses searches them.
+fn terminfo_dirs(var: &dyn Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+	let mut v: Vec<PathBuf> = Vec::new();
+
+	if let Some(d) = var("TERMINFO") { v.push(PathBuf::from(d)); }
+	if let Some(h) = var("HOME") { v.push(PathBuf::from(h).join(".terminfo")); }
+
+	// Empty entries in TERMINFO_DIRS mean "the default locations"
+	for d in var("TERMINFO_DIRS").unwrap_or_default().split(':') {
+		if d.len() != 0 { v.push(PathBuf::from(d)); }
+	}
+
+	for d in ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"] {
+		v.push(PathBuf::from(d));
+	}
+
+	return v;
+}
+
+
+// Find `max_colors` in the terminfo entry for `term`.
+// Returns None if there is no entry.
+fn terminfo_colors(var: &dyn Fn(&str) -> Option<String>, term: &str) -> Option<i32> {
+	let c = term.chars().next()?;
+
+	for d in terminfo_dirs(var) {
+		// Entries are usually in a directory named after their first letter.
+		// Some systems (like macOS) use that letter's hex code instead.
+		for s in [c.to_string(), format!("{:x}", c as u32)] {
+			let Ok(b) = fs::read(d.join(s).join(term)) else { continue };
+			return parse_max_colors(&b);
+		}
+	}
+
+	return None;
+}
+
+
+// Read `max_colors` from a compiled terminfo entry.
+// See term(5) for this format.
+pub fn parse_max_colors(b: &[u8]) -> Option<i32> {
+	let short = |i: usize| -> Option<i16> {
+		return Some(i16::from_le_bytes([*b.get(i)?, *b.get(i + 1)?]));
+	};
+
+	// Numbers are 16 bits in the legacy format, 32 in the extended one
+	let width = match short(0)? {
+		0o432 => 2,
+		0o1036 => 4,
+		_ => return None
+	};
+
+	// Counts are never negative in a valid entry
+	let count = |i: usize| -> Option<usize> {
+		return usize::try_from(short(i)?).ok();
+	};
+
+	let names = count(2)?;
+	let bools = count(4)?;
+	let numbers = count(6)?;
+
+	// max_colors is the 14th number.
+	// If it's missing, this terminal has no colors.
+	if numbers < 14 { return Some(0); }
+
+	// Numbers start on an even byte
+	let mut i = names.checked_add(bools)?.checked_add(12)?;
+	if i % 2 == 1 { i += 1; }
+	i = i.checked_add(13 * width)?;
+
+	let n = if width == 2 {
+		short(i)? as i32
+	} else {
+		i32::from_le_bytes(b.get(i..i + 4)?.try_into().ok()?)
+	};
+
+	// Negative numbers are absent capabilities
+	return Some(n.max(0));
+}
@@ -99,6 +99,8 @@ fn bad_expressions() {
 	bad_expr("-");
 	bad_expr("*");
 	bad_expr("+");
+	bad_expr("2 (1, 2)");
+	bad_expr("(1, 2) 2");
 }
 
 #[test]
@@ -223,6 +225,351 @@ fn complex_units() {
 	good_expr("3600", "1 hour * 1 Hz");
 }
 
+#[test]
+fn best_units() {
+	use daisycalc::Calculator;
+
+	// Named units
+	good_expr("1 N", "1 kg * 1 m / s^2");
+	good_expr("10 W", "5 V * 2 A");
+	good_expr("6 mV", "2 ohm * 3 mA");
+	good_expr("1 J", "1 N m");
+	good_expr("1 m", "1 J / 1 N");
+
+	// Prefixes
+	good_expr("3.2 mm", "0.0032 m");
+	good_expr("1.5 km", "1500 m");
+	good_expr("500 g", "0.5 kg");
+	good_expr("2 ms", "0.002 s");
+	good_expr("5000 s", "5000 s");
+	good_expr("2 cm", "2 cm");
+	good_expr("12 um", "12 nm * 1000");
+	good_expr("1.5 t", "1500 kg");
+	good_expr("2 kt", "2e6 kg");
+	good_expr("500 kg", "0.5 t");
+	good_expr("1500 kilograms", "1500 kilograms");
+
+	// Some units don't take a new prefix
+	good_expr("1500 K", "1500 K");
+	good_expr("0.1 lm", "0.1 lm");
+	good_expr("0.002 l", "0.002 l");
+
+	// Non-SI units and powers are left alone
+	good_expr("6 ft²·lbf", "2 ft^2 * 3 lbf");
+	good_expr("0.001 m²", "0.001 m^2");
+	good_expr("1 km/h", "1 km/h");
+
+	// An explicit `to` always wins
+	good_expr("0.0032 m", "0.0032 m to m");
+	good_expr("3000 m", "3 km to m");
+
+	// ...even after it's saved
+	let mut c = Calculator::new();
+	c.eval("3 km to m").unwrap();
+	for s in ["ans", "$1", "out[1]"] {
+		let v = c.eval(s).unwrap();
+		assert_eq!(c.display(&v), "3000 m");
+	}
+	c.define_variable("yv", "3 km to m").unwrap();
+	let v = c.eval("yv").unwrap();
+	assert_eq!(c.display(&v), "3000 m");
+
+	// New results are simplified as usual
+	let v = c.eval("yv * 2").unwrap();
+	assert_eq!(c.display(&v), "6 km");
+
+	let mut c = Context::new();
+	c.config.enable_simplify = false;
+	let e = parser::parse(&c, &String::from("1 kg * 1 m^2 / s^2")).unwrap();
+	let e = evaluate(&mut c, &e).unwrap();
+	assert_eq!(e.display_outer(&c), "1 m²·kg/s²");
+}
+
+#[test]
+fn compound_conversion() {
+	good_expr("(5 ft, 3.6 in)", "5.3 ft to ft, in");
+	good_expr("(1 h, 2 min, 5 s)", "3725 s to h, min, s");
+	good_expr("(12°, 20', 44.16\")", "12.3456° to °, ', \"");
+	good_expr("(-5 ft, -3.6 in)", "-5.3 ft to ft, in");
+	good_expr("(2 km, 500 m)", "2.5 km to (km, m)");
+
+	// Exact subtraction of converted units
+	good_expr("0.3 ft", "5.3 ft - 5 ft");
+
+	bad_expr("5 ft to ft, s");
+	bad_expr("5 ft, 3 in");
+	bad_expr("5 to ft, in");
+
+	// Lists of units are only allowed right after `to`
+	bad_expr("5.3 ft to ft, 2 in");
+	bad_expr("5.3 ft to ft, in + 2");
+	bad_expr("(5.3 ft to ft, in) + 2");
+	bad_expr("1 m == 1 m to ft, in");
+	bad_expr("(1, 2) + 3");
+	bad_expr("-(1, 2)");
+
+	use daisycalc::Calculator;
+	use daisycalc::DaisyError;
+
+	let mut c = Calculator::new();
+	let e = c.eval("(1 ± 0.1) to ft, in").unwrap_err();
+	assert!(matches!(e.error, DaisyError::IncompatibleUnitList(_, _)));
+	let e = c.eval("5.3 ft to ft, 2 in").unwrap_err();
+	assert!(matches!(e.error, DaisyError::BadUnitList));
+	assert_eq!(e.location.pos, 14);
+	assert_eq!(e.location.len, 4);
+}
+
+#[test]
+fn exact_angles() {
+	good_expr("3.1416 r", "180° to radian");
+	good_expr("180°", "pi radians to deg");
+	good_expr("90°", "(pi/2) radians to deg");
+
+	good_expr("0.5", "sin(30°)");
+	good_expr("0.5", "cos(60°)");
+	good_expr("0.5", "sin(pi/6)");
+	good_expr("-1", "tan(-45°)");
+	good_expr("2", "csc(30°)");
+	good_expr("0", "sin(pi radians)");
+
+	// These would be tiny floats if the values above weren't exact
+	good_expr("0", "(sin(30°) - 0.5) * 10^20");
+	good_expr("0", "(cos(60°) - 1/2) * 10^20");
+}
+
+#[test]
+fn long_prefixes() {
+	good_expr("3 kilometers", "3 kilometers");
+	good_expr("2 megatonnes", "2 megatonnes");
+	good_expr("10 nanoseconds", "10 nanoseconds");
+	good_expr("3072 B", "3 kibibytes to B");
+	good_expr("1.5 kilometer", "1 kilometer + 500 m");
+	good_expr("5 megameters", "5000 kilometers");
+	good_expr("1e6 millimeters", "1 km to millimeters");
+	good_expr("1 km²", "1 km * 1 kilometer");
+
+	// Long prefixes only go with long names
+	bad_expr("3 kilom");
+	bad_expr("3 kmeter");
+}
+
+#[test]
+fn data_units() {
+	// Binary prefixes stay binary
+	good_expr("4 MiB", "4096 KiB");
+	good_expr("512 B", "0.5 KiB");
+	good_expr("1024 ZiB", "1 YiB to ZiB");
+	good_expr("128 MiB", "1 Gibit to MiB");
+	good_expr("3 kibibits", "3 kibibits");
+
+	// Decimal prefixes aren't added to data units
+	good_expr("3e6 B", "3000000 B");
+	good_expr("2048 B", "2048 B");
+	good_expr("3 MB", "3 MB");
+
+	// Data rates
+	good_expr("8.3886 Mbit/s", "1 MiB/s to Mbit/s");
+	good_expr("12.5 MBps", "100 Mbps to MBps");
+	good_expr("9.6 kBd", "9600 baud to kBd");
+	good_expr("1.2e11", "2 GFLOPS * 1 min");
+}
+
+#[test]
+fn light_units() {
+	good_expr("1 lx", "1 lm / 1 m^2 to lx");
+	good_expr("1 lx", "1 lm / 1 m^2");
+	good_expr("1 lm", "1 cd * 1 sr");
+	good_expr("200 lm", "100 lx * 2 m^2");
+	good_expr("10.764 lx", "1 fc to lx");
+	good_expr("1 fc", "1 lm/ft^2 to fc");
+	good_expr("10000 lx", "1 phot to lx");
+	good_expr("1 cd", "1 nit * 1 m^2 to cd");
+
+	// Lux and nits have different dimensions
+	bad_expr("1 lx to nit");
+}
+
+#[test]
+fn currencies() {
+	use daisycalc::quantity::Rates;
+	use daisycalc::Calculator;
+	use daisycalc::DaisyError;
+
+	let r = Rates::from_csv("test", concat!(
+		"# A comment\n",
+		"updated,2024-05-01\n",
+		"EUR,1.08\n",
+		"GBP, 1.25\n",
+		"USD,1\n"
+	)).unwrap();
+	assert_eq!(r.updated.as_deref(), Some("2024-05-01"));
+	assert_eq!(r.rates.len(), 2);
+
+	// Rates belong to a context, other contexts don't see them.
+	let mut c = Calculator::new();
+	c.context_mut().set_rates(Some(r));
+	let mut good = |r: &str, s: &str| {
+		let v = c.eval(s).unwrap();
+		assert_eq!(c.display(&v), r);
+	};
+
+	good("54 USD", "50 EUR to USD");
+	good("92.593 EUR", "100 USD to EUR");
+	good("115.74 EUR", "100 GBP to EUR");
+	good("720 USD", "USD/h * 720 h");
+	good("14.629 EUR", "10 EUR + 5 USD");
+	good("true", "10 EUR > 10 USD");
+	good("10.8 USD", "tobase(10 EUR)");
+
+	// No rate for this one
+	let e = c.eval("1 CAD to USD").unwrap_err();
+	assert!(matches!(e.error, DaisyError::NoRates(Some(_))));
+	let e = c.eval("1 USD to m").unwrap_err();
+	assert!(matches!(e.error, DaisyError::IncompatibleUnits(_, _, _)));
+
+	// No rates at all
+	bad_expr("100 USD to EUR");
+	let mut c = Calculator::new();
+	let e = c.eval("100 USD to EUR").unwrap_err();
+	assert!(matches!(e.error, DaisyError::NoRates(None)));
+	let e = c.eval("1 EUR + 1 USD").unwrap_err();
+	assert!(matches!(e.error, DaisyError::NoRates(None)));
+
+	assert!(Rates::from_csv("test", "XYZ,1").is_err());
+	assert!(Rates::from_csv("test", "m,1").is_err());
+	assert!(Rates::from_csv("test", "EUR,-1").is_err());
+	assert!(Rates::from_csv("test", "EUR").is_err());
+}
+
+#[test]
+fn dimensions() {
+	use daisycalc::command;
+
+	let mut c = Context::new();
+	let mut dim = |s: &str| {
+		return command::do_command(&mut c, &format!("dim {s}")).to_string();
+	};
+
+	let t = dim("5 kg m/s^2");
+	assert!(t.contains("L¹M¹T⁻²"));
+	assert!(t.contains("force"));
+	assert!(t.contains("N") && t.contains("lbf"));
+
+	let t = dim("J");
+	assert!(t.contains("L²M¹T⁻²"));
+	assert!(t.contains("energy"));
+	assert!(t.contains("cal") && t.contains("eV"));
+
+	let t = dim("psi");
+	assert!(t.contains("pressure"));
+	assert!(t.contains("Pa") && t.contains("atm"));
+
+	let t = dim("1/s");
+	assert!(t.contains("T⁻¹"));
+	assert!(t.contains("Hz") && t.contains("Bq"));
+
+	// Non-SI base units are listed after the SI ones
+	assert!(dim("1 Mbps").contains("T⁻¹ bit¹"));
+	assert!(dim("3").contains("dimensionless"));
+	assert!(!dim("m^2 K").contains("Kind"));
+
+	assert!(dim("1 + m").contains("Incompatible"));
+	assert!(dim("x = 3").contains("takes an expression"));
+	assert!(dim("").contains("takes an expression"));
+}
+
+#[test]
+fn suggestions() {
+	use daisycalc::DaisyError;
+
+	let mut c = Context::new();
+	let mut err = |s: &str| {
+		let g = parser::parse(&c, &String::from(s)).unwrap();
+		return evaluate(&mut c, &g).unwrap_err().1;
+	};
+
+	let DaisyError::Undefined(_, v) = err("3 meterz") else { panic!() };
+	assert!(v.contains(&String::from("meters")));
+	let DaisyError::Undefined(_, v) = err("sine(3)") else { panic!() };
+	assert!(v.contains(&String::from("sin")));
+	let DaisyError::Undefined(_, v) = err("sqrtt(4)") else { panic!() };
+	assert_eq!(v, vec!["sqrt"]);
+	let DaisyError::Undefined(_, v) = err("zxqv") else { panic!() };
+	assert!(v.is_empty());
+
+	let DaisyError::IncompatibleUnits(_, _, h) = err("1 kg to N") else { panic!() };
+	assert_eq!(h.as_deref(), Some("multiply by an acceleration (e.g. g₀)"));
+	let DaisyError::IncompatibleUnits(_, _, h) = err("1 m to s") else { panic!() };
+	assert_eq!(h.as_deref(), Some("divide by a speed (e.g. c)"));
+	let DaisyError::IncompatibleUnits(_, _, h) = err("1 W to J") else { panic!() };
+	assert_eq!(h.as_deref(), Some("multiply by a time (e.g. s)"));
+	let DaisyError::IncompatibleUnits(_, _, h) = err("1 m to N") else { panic!() };
+	assert_eq!(h.as_deref(), Some("multiply by something in kg/s²"));
+
+	// Hints are only given for conversions
+	let DaisyError::IncompatibleUnits(_, _, h) = err("1 kg + 1 N") else { panic!() };
+	assert!(h.is_none());
+}
+
+#[test]
+fn uncertainty() {
+	good_expr("9.81 ± 0.02 m/s²", "9.81 ± 0.02 m/s^2");
+	good_expr("9.81 ± 0.02 m/s²", "9.81 +/- 0.02 m/s^2");
+	good_expr("1.00 ± 0.02 m", "1 m ± 2 cm");
+	good_expr("19.62 ± 0.04 N", "(9.81 ± 0.02 m/s^2) * 2 kg");
+	good_expr("3.28 ± 0.03 ft", "(1 ± 0.01) m to ft");
+
+	// Independent uncertainties add in quadrature
+	good_expr("5.0 ± 0.5", "(2 ± 0.3) + (3 ± 0.4)");
+	good_expr("6.0 ± 0.5", "(2 ± 0.1) * (3 ± 0.2)");
+	good_expr("0.67 ± 0.06", "(2 ± 0.1) / (3 ± 0.2)");
+
+	// Through powers and functions
+	good_expr("4.00 ± 0.05", "sqrt(16 ± 0.4)");
+	good_expr("8.0 ± 0.6", "2^(3 ± 0.1)");
+	good_expr("0.841 ± 0.005", "sin(1 ± 0.01)");
+	good_expr("2.30 ± 0.10", "ln(10 ± 1)");
+	good_expr("24 ± 4", "(4 ± 0.1)!");
+	good_expr("(5 ft, 3.6 ± 1.2 in)", "(5.3 ± 0.1) ft to ft, in");
+
+	// Very big and very small values
+	good_expr("(6.022 ± 0.011)e23 mol⁻¹", "(6.022e23 ± 1.1e21) / mol");
+	good_expr("(1.23 ± 0.02)e-5", "0.00001234 ± 0.0000002");
+
+	// Exact values stay exact
+	good_expr("5", "5 ± 0");
+	good_expr("0", "(2 ± 0.1) * 0");
+
+	// Rounding is flat, so it gives exact results
+	good_expr("2", "floor(2.5 ± 0.1)");
+	good_expr("3", "ceil(2.5 ± 0.1)");
+	good_expr("3", "round(2.6 ± 0.1)");
+
+	// Uncertainties can't be negative
+	bad_expr("2 ± -0.1");
+	bad_expr("2 m ± -1 cm");
+	good_expr("1", "3 + -2");
+
+	// `+-` is an addition and a negative, not `±`
+	good_expr("-1", "1+-2");
+	good_expr("-1", "1 +- 2");
+	good_expr("true", "1+-2 == -1");
+	good_expr("0.5", "sin(30°)");
+
+	// Uncertainties are independent, even if they're the same value
+	good_expr("0.00 ± 0.14", "(2 ± 0.1) - (2 ± 0.1)");
+
+	// Infinite slopes give infinite uncertainties
+	bad_expr("(0 ± 0.1)^0.5");
+	bad_expr("sqrt(0 ± 0.1)");
+	bad_expr("ln(0 ± 0.1)");
+	bad_expr("atanh(1 ± 0.1)");
+	bad_expr("0^(2 ± 0.1)");
+
+	bad_expr("1 m ± 1 s");
+}
+
 #[test]
 fn functions() {
 	good_expr("1", "sin(90 deg)");
@@ -233,4 +580,778 @@ fn functions() {
 	good_expr("2", "nounit(2 mm)");
 	good_expr("2", "nounit(2 meter * second)");
 	//good_expr("5000 m²·g/(s²·A²)", "tobase(5H)");
-}
\ No newline at end of file
+}
+
+#[test]
+fn calculator() {
+	use daisycalc::Calculator;
+	use daisycalc::DaisyError;
+	use daisycalc::quantity::Quantity;
+
+	let mut c = Calculator::new();
+
+	let v = c.eval("2 km + 500 m").unwrap();
+	assert_eq!(c.display(&v), "2.5 km");
+	assert_eq!(v.as_quantity().unwrap().to_f64(), 2.5);
+	let v = c.eval("ans * 2").unwrap();
+	assert_eq!(c.display(&v), "5 km");
+
+	c.define_variable("x", "3 m").unwrap();
+	assert_eq!(c.display(&c.get_variable("x").unwrap()), "3 m");
+	assert!(c.get_variable("y").is_none());
+	assert!(c.define_variable("sin", "3").is_err());
+
+	// Definitions behave like `name = value`
+	assert!(c.define_variable("area", "side^2").unwrap().is_none());
+	assert!(c.define_variable("side", "area").is_err());
+	c.define_variable("side", "2 m").unwrap();
+	let v = c.eval("area").unwrap();
+	assert_eq!(c.display(&v), "4 m²");
+
+	c.define_function("f", &["foo", "qux"], "foo * qux + x").unwrap();
+	let v = c.eval("f(2, 3 m)").unwrap();
+	assert_eq!(c.display(&v), "9 m");
+
+	c.register_function("double", 1, |a| {
+		return Ok(a[0].clone() * Quantity::new_rational(2f64).unwrap());
+	}).unwrap();
+	let v = c.eval("double(x) + 1 m").unwrap();
+	assert_eq!(c.display(&v), "7 m");
+
+	let e = c.eval("double(1, 2)").unwrap_err();
+	assert!(matches!(e.error, DaisyError::BadArguments(_, 1, 2)));
+	assert_eq!(e.message(), "Evaluation Error: double takes 1 argument, but it got 2");
+
+	let e = c.eval("1 + ").unwrap_err();
+	assert_eq!(e.location.pos, 2);
+	assert_eq!(e.message(), "Syntax Error");
+}
+
+#[test]
+fn native_functions() {
+	use daisycalc::Calculator;
+	use daisycalc::DaisyError;
+	use daisycalc::NativeFunction;
+	use daisycalc::quantity::Quantity;
+
+	let mut c = Calculator::new();
+
+	// Resistance of a copper wire, in ohms.
+	// Arguments are converted to a unitless gauge and meters.
+	let units = vec![Some(c.unit("1").unwrap()), Some(c.unit("m").unwrap())];
+	let ohm = c.unit("ohm").unwrap();
+	c.register_native_function(
+		"wire_resistance",
+		NativeFunction::new_with_units(units, move |a| {
+			let d = 0.127e-3 * 92f64.powf((36.0 - a[0].to_f64()) / 39.0);
+			let area = std::f64::consts::PI * d * d / 4.0;
+			let mut r = Quantity::new_float(1.68e-8 * a[1].to_f64() / area).unwrap();
+			r.set_unit(ohm.clone());
+			return Ok(r);
+		})
+	).unwrap();
+
+	let v = c.eval("wire_resistance(10, 1000 ft)").unwrap();
+	assert_eq!(c.display(&v), "973.29 mΩ");
+
+	let e = c.eval("wire_resistance(10, 2 s)").unwrap_err();
+	assert!(matches!(e.error, DaisyError::IncompatibleUnits(_, _, _)));
+	assert_eq!(e.location.pos, 20);
+	assert_eq!(e.location.len, 3);
+
+	let e = c.eval("wire_resistance(10)").unwrap_err();
+	assert!(matches!(e.error, DaisyError::BadArguments(_, 2, 1)));
+
+	// Calling a native function directly checks its arity too
+	let f = c.context().get_native_function("wire_resistance").unwrap();
+	let r = f.call(c.context(), "wire_resistance", vec![Quantity::new_rational(10f64).unwrap()]);
+	assert!(matches!(r, Err((None, DaisyError::BadArguments(_, 2, 1)))));
+
+	// Native functions can't be replaced by user definitions
+	assert!(c.define_variable("wire_resistance", "1").is_err());
+	assert!(c.define_function("wire_resistance", &["x"], "x").is_err());
+}
+
+#[test]
+fn definitions() {
+	use daisycalc::do_string;
+	use daisycalc::DaisyError;
+	use daisycalc::parser::Statement;
+
+	let mut c = Context::new();
+
+	// Returns the error and its location
+	fn bad_def(c: &mut Context, s: &str) -> (usize, usize, DaisyError) {
+		let (l, e) = parser::parse_statement(c, &String::from(s)).unwrap_err();
+		return (l.pos, l.len, e);
+	}
+
+	do_string(&mut c, &String::from("foo = 2 m + 1 m")).unwrap();
+	do_string(&mut c, &String::from("area(width, height) = width * height")).unwrap();
+	do_string(&mut c, &String::from("area(foo, 4 m)")).unwrap();
+	let ans = c.get_variable(&String::from("ans")).unwrap();
+	assert_eq!(ans.display_outer(&c), "12 m²");
+
+	let s = parser::parse_statement(&mut c, &String::from("  qux  =  foo ")).unwrap();
+	let Statement::VariableDefinition(l, n, _) = s else { panic!() };
+	assert_eq!((l.pos, l.len, &n[..]), (2, 3, "qux"));
+
+	let s = parser::parse_statement(&mut c, &String::from("f(x, y) = x + y")).unwrap();
+	let Statement::FunctionDefinition(l, n, a, _) = s else { panic!() };
+	assert_eq!((l.pos, l.len, &n[..]), (0, 7, "f"));
+	assert_eq!(a, vec!["x", "y"]);
+
+	let s = parser::parse_statement(&mut c, &String::from("foo + 1")).unwrap();
+	assert!(matches!(s, Statement::Expression(_)));
+
+	assert!(matches!(bad_def(&mut c, "m = 3"), (0, 1, DaisyError::BadVariable)));
+	assert!(matches!(bad_def(&mut c, "sin(x) = 3"), (0, 3, DaisyError::BadFunction)));
+	assert!(matches!(bad_def(&mut c, "f(x, 2) = 3"), (5, 1, DaisyError::Syntax)));
+	assert!(matches!(bad_def(&mut c, "f(x, x) = 3"), (5, 1, DaisyError::BadVariable)));
+	assert!(matches!(bad_def(&mut c, "f() = 3"), (1, 2, DaisyError::EmptyGroup)));
+	assert!(matches!(bad_def(&mut c, "foo = 1 = 2"), (8, 1, DaisyError::Syntax)));
+	assert!(matches!(bad_def(&mut c, "foo = "), (4, 1, DaisyError::Syntax)));
+	assert!(matches!(bad_def(&mut c, " = 1"), (1, 1, DaisyError::Syntax)));
+	assert!(matches!(bad_def(&mut c, "foo = (1 +)"), (9, 1, DaisyError::Syntax)));
+
+	// Equals signs are only allowed in definitions
+	assert!(parser::parse(&c, &String::from("foo = 1")).is_err());
+}
+
+#[test]
+fn comparisons() {
+	good_expr("true", "1 < 2");
+	good_expr("false", "1 > 2");
+	good_expr("true", "2 <= 2");
+	good_expr("true", "2 >= 2");
+	good_expr("true", "0.1 + 0.2 == 0.3");
+	good_expr("true", "1 != 2");
+	good_expr("true", "1 ft < 1 m");
+	good_expr("true", "1 ft == 12 in");
+	good_expr("false", "1 ft < 12 in");
+	good_expr("true", "-1 < 0");
+
+	// Exact values are compared exactly
+	good_expr("false", "10^20 == 10^20 + 1");
+	good_expr("true", "10^20 < 10^20 + 1");
+	good_expr("false", "1 == 1.0000000000001");
+	good_expr("true", "1 != 1.0000000000001");
+	good_expr("true", "1 <= 1.0000000000001");
+	good_expr("false", "1 >= 1.0000000000001");
+	good_expr("true", "sqrt(2)^2 == 2");
+
+	good_expr("true", "1 < 2 and 2 < 3");
+	good_expr("true", "true or false and false");
+	good_expr("false", "not 1 < 2");
+	good_expr("true", "true == not false");
+
+	good_expr("2", "if(1 < 2, 2, 3)");
+	good_expr("3", "if(false, 2, 3)");
+	good_expr("1 m", "if(true, 1 m, 1/0)");
+
+	bad_expr("1 s < 1 m");
+	bad_expr("true + 1");
+	bad_expr("sin(true)");
+	bad_expr("if(1, 2, 3)");
+	bad_expr("if(true, 2)");
+	bad_expr("1 and true");
+}
+
+#[test]
+fn conditionals() {
+	use daisycalc::Calculator;
+	use daisycalc::DaisyError;
+
+	let mut c = Calculator::new();
+	c.define_function("f", &["x"], "if(x < 0, 0, x^2)").unwrap();
+	let v = c.eval("f(-2)").unwrap();
+	assert_eq!(c.display(&v), "0");
+	let v = c.eval("f(3)").unwrap();
+	assert_eq!(c.display(&v), "9");
+	assert_eq!(c.eval("f(3) > 5").unwrap().as_bool(), Some(true));
+
+	let e = c.eval("1 s < 1 m").unwrap_err();
+	assert!(matches!(e.error, DaisyError::IncompatibleUnits(_, _, _)));
+}
+
+#[test]
+fn recursion() {
+	use daisycalc::do_string;
+	use daisycalc::DaisyError;
+	use daisycalc::Calculator;
+
+	let mut c = Context::new();
+	do_string(&mut c, &String::from("fact(n) = if(n <= 1, 1, n*fact(n-1))")).unwrap();
+	do_string(&mut c, &String::from("sq(x) = x^2")).unwrap();
+	do_string(&mut c, &String::from("inc(x) = x + 1")).unwrap();
+
+	for (r, s) in [
+		("120", "fact(5)"),
+		("16", "sq(inc(3))"),
+		("10", "inc(sq(inc(2)))"),
+		("169", "fact(5) + sq(inc(fact(3)))"),
+	] {
+		do_string(&mut c, &String::from(s)).unwrap();
+		let ans = c.get_variable(&String::from("ans")).unwrap();
+		assert_eq!(ans.display_outer(&c), r);
+	}
+
+	// Arguments don't leak out of function calls
+	assert!(do_string(&mut c, &String::from("n")).is_err());
+
+	// Ordinary recursion isn't limited
+	do_string(&mut c, &String::from("fact(150) == 150!")).unwrap();
+	let ans = c.get_variable(&String::from("ans")).unwrap();
+	assert_eq!(ans.display_outer(&c), "true");
+
+	let mut c = Calculator::new();
+	c.define_function("sum", &["n"], "if(n <= 0, 0, n + sum(n - 1))").unwrap();
+	let v = c.eval("sum(100)").unwrap();
+	assert_eq!(c.display(&v), "5050");
+
+	// Runaway recursion is stopped at the default `max_call_depth`,
+	// before it overflows the stack (test threads only have 2 MiB).
+	c.define_function("forever", &["x"], "forever(x + 1)").unwrap();
+	let e = c.eval("forever(1)").unwrap_err();
+	assert!(matches!(e.error, DaisyError::TooDeep));
+	assert_eq!((e.location.pos, e.location.len), (0, 10));
+
+	// Definitions that always recurse are rejected
+	assert!(c.define_function("never", &["x"], "never(1)").is_err());
+}
+
+#[test]
+fn floating_variables() {
+	use daisycalc::do_string;
+	use daisycalc::command;
+	use daisycalc::DaisyError;
+	use daisycalc::parser::Statement;
+	use daisycalc::evaluate_variable;
+
+	let mut c = Context::new();
+	let run = |c: &mut Context, s: &str| do_string(c, &String::from(s));
+
+	// Floating variables track the variables they use
+	run(&mut c, "dist = speed * time").unwrap();
+	run(&mut c, "speed = 3 m/s").unwrap();
+	assert!(run(&mut c, "dist").is_err());
+	run(&mut c, "time = 2 s").unwrap();
+	run(&mut c, "dist").unwrap();
+	assert_eq!(c.get_variable(&String::from("ans")).unwrap().display_outer(&c), "6 m");
+	assert_eq!(c.get_dependencies("dist"), vec!["speed", "time"]);
+
+	// Function arguments don't leak into floating variables
+	run(&mut c, "double(time) = 2 * dist").unwrap();
+	run(&mut c, "double(100 s)").unwrap();
+	assert_eq!(c.get_variable(&String::from("ans")).unwrap().display_outer(&c), "12 m");
+
+	// Variables with values don't depend on anything
+	run(&mut c, "foo = 1").unwrap();
+	run(&mut c, "foo = foo + 1").unwrap();
+	assert!(!c.is_floating("foo"));
+
+	// Cycles are rejected where they're made
+	fn cycle(c: &mut Context, s: &str) -> (usize, usize, Vec<String>) {
+		let Statement::VariableDefinition(_, n, g) = parser::parse_statement(c, &String::from(s)).unwrap() else { panic!() };
+		let Err((l, DaisyError::CircularDefinition(v))) = evaluate_variable(c, &n, &g) else { panic!() };
+		return (l.pos, l.len, v);
+	}
+
+	run(&mut c, "qux = zork").unwrap();
+	assert_eq!(cycle(&mut c, "zork = 1 + qux"), (11, 3, vec!["zork".to_string(), "qux".to_string(), "zork".to_string()]));
+	assert_eq!(cycle(&mut c, "baz = baz + 1"), (6, 3, vec!["baz".to_string(), "baz".to_string()]));
+	assert_eq!(cycle(&mut c, "time = dist"), (7, 4, vec!["time".to_string(), "dist".to_string(), "time".to_string()]));
+	assert!(run(&mut c, "zork = qux").is_err());
+	assert!(run(&mut c, "zork = 2").is_ok());
+
+	// `deps` is spaced like the other listings
+	let list = |c: &mut Context, s: &str| command::do_command(c, &String::from(s)).plain();
+	assert_eq!(
+		list(&mut c, "deps dist"),
+		"\n╞═══ Dependencies ═══╡\n  dist\n  ├─ speed (value)\n  └─ time (value)\n\n\n"
+	);
+	for s in ["deps qux", "vars", "history", "consts"] {
+		let t = list(&mut c, s);
+		assert!(t.starts_with("\n╞") && t.ends_with("\n\n\n") && !t.ends_with("\n\n\n\n"), "{s}");
+	}
+}
+
+#[test]
+fn history() {
+	use daisycalc::do_string;
+	use daisycalc::DaisyError;
+	use daisycalc::Calculator;
+
+	let mut c = Context::new();
+	for s in ["1 + 1", "3 m", "ans1 * 2", "$2 + 1 m", "out[3] * out[1]"] {
+		do_string(&mut c, &String::from(s)).unwrap();
+	}
+
+	let h = c.get_history();
+	assert_eq!(h.len(), 5);
+	assert_eq!(h[2].0, "ans1 * 2");
+	assert_eq!(h[2].1.display_outer(&c), "4");
+	assert_eq!(h[3].1.display_outer(&c), "4 m");
+	assert_eq!(h[4].1.display_outer(&c), "8");
+
+	// Results are numbered from one
+	assert!(do_string(&mut c, &String::from("ans9")).is_err());
+	for s in ["$0", "ans0", "out[0]"] {
+		let g = parser::parse(&c, &String::from(s)).unwrap();
+		let (_, e) = evaluate(&mut c, &g).unwrap_err();
+		assert!(matches!(e, DaisyError::NoHistory(0, 5)));
+	}
+
+	// Missing results have their own error
+	let g = parser::parse(&c, &String::from("1 + ans9")).unwrap();
+	let (l, e) = evaluate(&mut c, &g).unwrap_err();
+	assert!(matches!(e, DaisyError::NoHistory(9, 5)));
+	assert_eq!((l.pos, l.len), (4, 4));
+	assert_eq!(e.text().plain(), "Evaluation Error: Result $9 doesn't exist, the last one is $5");
+
+	let mut d = Calculator::new();
+	let e = d.eval("$2").unwrap_err();
+	assert!(matches!(e.error, DaisyError::NoHistory(2, 0)));
+	let e = d.eval("ans").unwrap_err();
+	assert!(matches!(e.error, DaisyError::NoHistory(0, 0)));
+	let e = d.eval("$0").unwrap_err();
+	assert!(matches!(e.error, DaisyError::NoHistory(0, 0)));
+	assert_eq!(e.message(), "Evaluation Error: There are no results yet");
+
+	// `ans 2` is still ans times two
+	do_string(&mut c, &String::from("ans 2")).unwrap();
+	assert_eq!(c.get_history()[5].1.display_outer(&c), "16");
+
+	assert_eq!(Context::history_index("out[12]"), Some(12));
+	assert_eq!(Context::history_index("out[12"), None);
+	assert_eq!(Context::history_index("answer"), None);
+}
+
+#[test]
+fn preview() {
+	use daisycalc::do_string;
+	use daisycalc::PromptBuffer;
+	use daisycalc::NativeFunction;
+	use std::rc::Rc;
+	use std::cell::Cell;
+
+	fn preview(c: &Context, s: &str) -> Option<String> {
+		let mut pb = PromptBuffer::new(8);
+		for x in s.chars() { pb.add_char(x); }
+		return pb.preview(c);
+	}
+
+	let mut c = Context::new();
+	do_string(&mut c, &String::from("zork = 3")).unwrap();
+
+	assert_eq!(preview(&c, "zork + 1"), Some(String::from("= 4")));
+	assert_eq!(preview(&c, "qux = zork * 2"), Some(String::from("qux = 6")));
+	assert_eq!(preview(&c, "1 +"), Some(String::from("Syntax Error")));
+	assert_eq!(preview(&c, "  "), None);
+	assert_eq!(preview(&c, "del zork"), None);
+
+	// Previews must not change anything
+	assert_eq!(preview(&c, "zork = 5"), Some(String::from("zork = 5")));
+	assert_eq!(preview(&c, "sq(x) = x^2"), Some(String::from("sq(x) = x²")));
+	assert!(c.get_history().len() == 0);
+	assert!(!c.is_varible("qux"));
+	assert!(!c.is_function("sq"));
+	assert_eq!(c.get_variables()["zork"].display(&c), "3");
+
+	// Previews skip expensive work
+	do_string(&mut c, &String::from("sum(n) = if(n <= 0, 0, n + sum(n - 1))")).unwrap();
+	assert_eq!(preview(&c, "sum(10)"), Some(String::from("= 55")));
+	assert_eq!(preview(&c, "sum(100)"), None);
+	assert_eq!(preview(&c, &"1 + ".repeat(100)), None);
+	assert_eq!(preview(&c, "zorkk"), Some(String::from("Evaluation Error: zorkk is not defined")));
+
+	// Native functions are only called for entered input
+	let calls = Rc::new(Cell::new(0));
+	let n = calls.clone();
+	c.push_native_function(String::from("ping"), NativeFunction::new(1, move |a| {
+		n.set(n.get() + 1);
+		return Ok(a[0].clone());
+	})).unwrap();
+	do_string(&mut c, &String::from("twice(x) = 2 ping(x)")).unwrap();
+
+	assert_eq!(preview(&c, "ping(2) + 1"), None);
+	assert_eq!(preview(&c, "twice(3)"), None);
+	assert_eq!(preview(&c, "(1, ping(2))"), None);
+	assert_eq!(preview(&c, "ping(1, 2)"), Some(String::from("Evaluation Error: ping takes 1 argument, but it got 2")));
+	assert_eq!(calls.get(), 0);
+
+	do_string(&mut c, &String::from("ping(2) + 1")).unwrap();
+	assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn highlighting() {
+	use daisycalc::do_string;
+	use daisycalc::FormattedText;
+
+	let mut c = Context::new();
+	do_string(&mut c, &String::from("zork = 3")).unwrap();
+	do_string(&mut c, &String::from("sq(x) = x^2")).unwrap();
+
+	let h = |c: &Context, s: &str| parser::highlight(c, &String::from(s)).to_string();
+
+	assert_eq!(
+		h(&c, "sin(30 °) + zork × π"),
+		"[hfunc]sin[n]([hnum]30[n] [hunit]°[n]) [hop]+[n] [hvar]zork[n] [hop]×[n] [hconst]π[n]"
+	);
+	assert_eq!(
+		h(&c, "sq(qux) to m"),
+		"[hufunc]sq[n]([herr]qux[n]) [hop]to[n] [hunit]m[n]"
+	);
+
+	// Names being defined aren't undefined
+	assert_eq!(
+		h(&c, "f(y) = y + f(y)"),
+		"[hufunc]f[n]([hvar]y[n]) [hop]=[n] [hvar]y[n] [hop]+[n] [hufunc]f[n]([hvar]y[n])"
+	);
+
+	// Unmatched parenthesis
+	assert_eq!(h(&c, "((1) ))"), "(([hnum]1[n]) )[herr])[n]");
+	assert_eq!(h(&c, "(1"), "[herr]([n][hnum]1[n]");
+
+	// Highlighting never changes the text
+	let s = "  1  + out[3]  ";
+	assert_eq!(parser::highlight(&c, &String::from(s)).plain(), s);
+
+	// Styles carry over to the last line of multi-line text
+	let t = |s: &str| FormattedText::new(String::from(s)).last_line().to_string();
+	assert_eq!(t("[hnum]1[n] [hop]+[n]\n[hnum]2[n]"), "[hnum]2[n]");
+	assert_eq!(t("[hvar]x[n] [herr]ab\ncd[n] [hop]+[n]"), "[herr]cd[n] [hop]+[n]");
+	assert_eq!(t("[e]a[cursorright2]\nb"), "[e]b");
+	assert_eq!(t("[x]a\nb"), "b");
+	assert_eq!(t("a[n]"), "a[n]");
+}
+
+#[test]
+fn completion() {
+	use daisycalc::do_string;
+	use daisycalc::PromptBuffer;
+	use daisycalc::parser::Function;
+
+	for s in Function::all_strings() {
+		assert!(Function::from_string(s).is_some(), "{s}");
+	}
+
+	let mut c = Context::new();
+	do_string(&mut c, &String::from("zorkle = 3")).unwrap();
+
+	let mut pb = PromptBuffer::new(8);
+	let typed = |pb: &mut PromptBuffer, s: &str| {
+		for x in s.chars() { pb.add_char(x); }
+		pb.complete(&c);
+		return pb.get_contents().clone();
+	};
+
+	assert_eq!(typed(&mut pb, "1 + zor"), "1 + zorkle");
+	pb.enter(&c);
+	assert_eq!(typed(&mut pb, "electricc"), "electricconstant");
+	pb.enter(&c);
+	assert_eq!(typed(&mut pb, "2 secan"), "2 secant");
+	pb.enter(&c);
+
+	// Ambiguous prefixes cycle
+	assert_eq!(typed(&mut pb, "sin"), "sinh");
+	pb.complete(&c);
+	assert_eq!(pb.get_contents(), "sin");
+	pb.enter(&c);
+
+	// Completion before the cursor
+	typed(&mut pb, "tob + 1");
+	for _ in 0..4 { pb.cursor_left(); }
+	pb.complete(&c);
+	assert_eq!(pb.get_contents(), "tobase + 1");
+	pb.enter(&c);
+
+	// Commands only complete at the start of a line
+	assert_eq!(typed(&mut pb, "hist"), "history");
+	pb.enter(&c);
+	assert_eq!(typed(&mut pb, "1 hist"), "1 hist");
+}
+
+#[test]
+fn line_editing() {
+	use daisycalc::PromptBuffer;
+
+	let c = Context::new();
+	let mut pb = PromptBuffer::new(8);
+	for x in "foo + bar_baz * 2".chars() { pb.add_char(x); }
+
+	pb.word_left();
+	pb.word_left();
+	pb.add_char('(');
+	assert_eq!(pb.get_contents(), "foo + (bar_baz * 2");
+
+	pb.cursor_end();
+	pb.add_char(')');
+	pb.cursor_home();
+	pb.word_right();
+	pb.add_char('!');
+	assert_eq!(pb.get_contents(), "foo! + (bar_baz * 2)");
+
+	// Kill and yank
+	pb.cursor_end();
+	pb.kill_word();
+	assert_eq!(pb.get_contents(), "foo! + (bar_baz * ");
+	pb.cursor_home();
+	pb.yank();
+	assert_eq!(pb.get_contents(), "2)foo! + (bar_baz * ");
+
+	pb.word_right();
+	pb.kill_end();
+	assert_eq!(pb.get_contents(), "2)foo");
+	pb.kill_start();
+	assert_eq!(pb.get_contents(), "");
+	pb.yank();
+	assert_eq!(pb.get_contents(), "2)foo");
+	pb.enter(&c);
+
+	// Reverse search
+	for s in ["sin(3)", "1 + 1", "sinh(2)", "4 m"] {
+		for x in s.chars() { pb.add_char(x); }
+		pb.enter(&c);
+	}
+
+	pb.search();
+	pb.add_char('s');
+	pb.add_char('i');
+	assert_eq!(pb.get_contents(), "sinh(2)");
+	pb.search();
+	assert_eq!(pb.get_contents(), "sin(3)");
+	pb.add_char('n');
+	assert_eq!(pb.get_contents(), "sin(3)");
+
+	// Keys that aren't part of a search keep the match
+	pb.cursor_left();
+	pb.backspace();
+	assert_eq!(pb.get_contents(), "sin()");
+	pb.enter(&c);
+
+	for x in "7".chars() { pb.add_char(x); }
+	pb.search();
+	pb.add_char('m');
+	assert_eq!(pb.get_contents(), "4 m");
+	pb.cancel_search();
+	assert_eq!(pb.get_contents(), "7");
+}
+
+#[test]
+fn multi_line() {
+	use daisycalc::do_string;
+	use daisycalc::PromptBuffer;
+
+	let mut c = Context::new();
+	let mut pb = PromptBuffer::new(8);
+	let line = |c: &Context, pb: &mut PromptBuffer, s: &str| {
+		for x in s.chars() { pb.add_char(x); }
+		return pb.enter(c);
+	};
+
+	// Unbalanced parenthesis and trailing operators continue
+	assert_eq!(line(&c, &mut pb, "sq(x) ="), "");
+	assert_eq!(line(&c, &mut pb, "  (x *"), "");
+	assert_eq!(line(&c, &mut pb, "x"), "");
+	let s = line(&c, &mut pb, ")");
+	assert_eq!(s, "sq(x) =\n  (x *\nx\n)");
+	do_string(&mut c, &s).unwrap();
+	assert_eq!(c.get_functions()["sq"].1.display(&c), "x × x");
+
+	// An empty line ends incomplete input
+	assert_eq!(line(&c, &mut pb, "1 +"), "");
+	assert_eq!(line(&c, &mut pb, ""), "1 +");
+
+	// Only the last line is drawn, highlighted with the ones before it
+	assert_eq!(line(&c, &mut pb, "sq(zork"), "");
+	for x in "2)".chars() { pb.add_char(x); }
+	let p = pb.write_prompt_nocursor(&c).to_string();
+	assert!(p.starts_with("\r[p]... [n][hnum]2[n])"), "{p}");
+	pb.enter(&c);
+
+	// Error locations count across lines
+	let e = do_string(&mut c, &String::from("1 +\n2 * zork")).unwrap_err();
+	assert_eq!(e.plain().lines().nth(1), Some("        ^^^^"));
+}
+
+#[test]
+fn unicode_input() {
+	use daisycalc::do_string;
+	use daisycalc::PromptBuffer;
+
+	let mut c = Context::new();
+	let mut pb = PromptBuffer::new(8);
+
+	for x in "2π × √4 + 3 km/1000 m".chars() { pb.add_char(x); }
+	let s = pb.enter(&c);
+	do_string(&mut c, &s).unwrap();
+	assert_eq!(c.get_history()[0].1.display_outer(&c), "15.566");
+
+	// Combining characters move with their base
+	for x in "e\u{301}x".chars() { pb.add_char(x); }
+	pb.cursor_left();
+	pb.cursor_left();
+	assert_eq!(pb.write_prompt(&c).plain().matches("cursorright").count(), 0);
+	pb.add_char('a');
+	assert_eq!(pb.get_contents(), "ae\u{301}x");
+	pb.cursor_right();
+	pb.backspace();
+	assert_eq!(pb.get_contents(), "ax");
+	pb.delete();
+	assert_eq!(pb.get_contents(), "a");
+	pb.enter(&c);
+
+	// Wide characters take two columns
+	for x in "漢字".chars() { pb.add_char(x); }
+	pb.cursor_left();
+	assert!(pb.write_prompt_nocursor(&c).to_string().ends_with("[cursorright8]"));
+	assert!(pb.write_prompt(&c).to_string().ends_with("[cursorright6]"));
+	pb.enter(&c);
+
+	// Pasted text is never a key press
+	pb.paste("1 +\r\n\t2\x07");
+	assert_eq!(pb.get_contents(), "1 +  2");
+	pb.enter(&c);
+
+	// Substitutions after non-ASCII characters
+	for (s, r) in [
+		("π*2", "π×2"),
+		("°/2", "°÷2"),
+		("2π × √4 + 3 km/1000 m", "2π × √4 + 3 km÷1000 m"),
+		("漢字 + pi*sqrt(4)", "漢字 + π×√(4)"),
+	] {
+		assert_eq!(parser::substitute(&c, &String::from(s)), r);
+
+		// Redrawing after every key used to panic here
+		for x in s.chars() { pb.add_char(x); pb.write_prompt(&c); }
+		pb.enter(&c);
+	}
+
+	// The cursor stays where it was
+	for x in "π*2".chars() { pb.add_char(x); }
+	pb.cursor_left();
+	assert!(pb.write_prompt(&c).to_string().ends_with("[cursorright6]"));
+	pb.enter(&c);
+
+	let e = do_string(&mut c, &String::from("°*")).unwrap_err();
+	assert_eq!(e.plain().lines().next(), Some("     ^"));
+}
+
+#[test]
+fn ascii_mode() {
+	use daisycalc::do_string;
+	use daisycalc::command;
+
+	let mut c = Context::new();
+	c.config.enable_unicode = false;
+	c.config.check();
+	assert!(!c.config.enable_substituion);
+	assert!(!c.config.enable_super_powers);
+
+	let mut run = |s: &str| {
+		do_string(&mut c, &String::from(s)).unwrap();
+		let h = c.get_history();
+		return h[h.len() - 1].1.display_outer(&c);
+	};
+
+	assert_eq!(run("3 kg^2 m/s^2"), "3 kg^2*m/s^2");
+	assert_eq!(run("1 m^2/(s^2 kg)"), "1 m^2/(kg*s^2)");
+	assert_eq!(run("2 ohm"), "2 ohm");
+	assert_eq!(run("90 deg"), "90 deg");
+	assert_eq!(run("pi"), "3.1416");
+	assert_eq!(run("2 +/- 0.1"), "2.00 +/- 0.10");
+
+	let e = parser::parse_no_context(&String::from("sqrt(2) * pi / 3 <= 4")).unwrap();
+	assert_eq!(e.display(&c), "sqrt(2) * (pi / 3) <= 4");
+
+	for s in ["help", "flags", "ops", "fns", "consts", "history", "vars", "dim 2 ohm/K"] {
+		let t = command::do_command(&mut c, &String::from(s)).to_string();
+		assert!(t.is_ascii(), "`{s}` printed unicode");
+	}
+}
+
+#[test]
+fn terminal_detection() {
+	use crate::terminal;
+	use std::collections::HashMap;
+
+	// A compiled terminfo entry with `max_colors` set to `n`.
+	// Names and booleans have odd lengths to test padding.
+	let entry = |n: i16| {
+		let mut b: Vec<u8> = Vec::new();
+		for x in [0o432i16, 4, 1, 14, 0, 0] { b.extend(x.to_le_bytes()); }
+		b.extend(b"foo\0");
+		b.extend([1, 0]);
+		for _ in 0..13 { b.extend((-1i16).to_le_bytes()); }
+		b.extend(n.to_le_bytes());
+		return b;
+	};
+
+	assert_eq!(terminal::parse_max_colors(&entry(256)), Some(256));
+	assert_eq!(terminal::parse_max_colors(&entry(-1)), Some(0));
+	assert_eq!(terminal::parse_max_colors(b"not terminfo"), None);
+
+	// Malformed headers are rejected
+	for h in [[0o432i16, -1, 1, 14], [0o432, 4, -2, 14], [0o432, 4, 1, -14], [0o1036, i16::MAX, i16::MAX, 14]] {
+		let mut b: Vec<u8> = Vec::new();
+		for x in h { b.extend(x.to_le_bytes()); }
+		assert_eq!(terminal::parse_max_colors(&b), None);
+	}
+
+	let dir = std::env::temp_dir().join(format!("daisy-terminfo-{}", std::process::id()));
+	std::fs::create_dir_all(dir.join("z")).unwrap();
+	std::fs::write(dir.join("z").join("zork"), entry(8)).unwrap();
+	std::fs::write(dir.join("z").join("zork-mono"), entry(-1)).unwrap();
+
+	let color = |v: &[(&str, &str)], tty: bool| {
+		let mut m: HashMap<String, String> = v.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+		m.insert("TERMINFO".to_string(), dir.to_str().unwrap().to_string());
+		return terminal::color_type(&|x| m.get(x).cloned(), tty);
+	};
+
+	assert_eq!(color(&[("TERM", "zork")], true), 1);
+	assert_eq!(color(&[("TERM", "zork-mono")], true), 0);
+	assert_eq!(color(&[("TERM", "zork"), ("COLORTERM", "truecolor")], true), 2);
+	assert_eq!(color(&[("TERM", "zork"), ("NO_COLOR", "1")], true), 0);
+	assert_eq!(color(&[("TERM", "zork"), ("NO_COLOR", "")], true), 1);
+	assert_eq!(color(&[("TERM", "zork")], false), 0);
+	assert_eq!(color(&[("TERM", "dumb"), ("COLORTERM", "truecolor")], true), 0);
+	assert_eq!(color(&[], true), 0);
+
+	// Unknown terminals are guessed from their name
+	assert_eq!(color(&[("TERM", "qux-256color")], true), 2);
+	assert_eq!(color(&[("TERM", "qux")], true), 1);
+
+	std::fs::remove_dir_all(dir).unwrap();
+
+	let locale = |v: &[(&str, &str)]| {
+		let m: HashMap<String, String> = v.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+		return terminal::locale_is_unicode(&|x| m.get(x).cloned());
+	};
+
+	assert!(locale(&[("LANG", "en_US.UTF-8")]));
+	assert!(locale(&[("LC_ALL", ""), ("LANG", "de_DE.utf8")]));
+	assert!(!locale(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]));
+	assert!(!locale(&[]));
+}
+
+#[test]
+fn piped_input() {
+	use crate::run_lines;
+
+	let mut c = Context::new();
+	let mut out: Vec<u8> = Vec::new();
+	run_lines(&mut c, "1 + 1\n\nsqrt(4) + 1 m\nquit\n2 + 2\n".as_bytes(), &mut out).unwrap();
+	let out = String::from_utf8(out).unwrap();
+
+	// Carets line up with the echoed line, which is substituted
+	let lines: Vec<&str> = out.lines().collect();
+	let i = lines.iter().position(|l| l.starts_with("==> ")).unwrap();
+	assert_eq!(lines[i], "==> √(4) + 1 m");
+	assert_eq!(lines[i + 1], "     ^^^^^^^^^");
+
+	// Nothing runs after `quit`
+	assert!(!out.contains("$2"));
+}